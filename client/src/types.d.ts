@@ -40,6 +40,8 @@ export interface Student {
   sex: Sex;
   /** 参加日 */
   join_date: Date;
+  /** 加入月（オプション）; 取得元の表記のまま */
+  join_month?: string;
   /** オフィス */
   office: string;
   /** メールアドレス */
@@ -113,6 +115,84 @@ export interface PgnInfo<T extends PgnLevel = PgnLevel> {
 
   /** 次のレベルまでに必要な残りのPIX */
   behind_next: T extends "GrandMaster" ? undefined : number;
+
+  /** 直近のリフレッシュにおけるPIXの取得元の内訳 */
+  sources?: PixSources;
+
+  /** 適用された閾値のシーズン; 未登録の場合は既定の閾値を用いる */
+  season?: Season;
+
+  /** 今後PIXを得なかった場合の予測 */
+  forecast: Forecast;
+}
+
+/**
+ * 今後PIXを得なかった場合の予測
+ */
+export interface Forecast {
+  /** 予測期間中に集計期間から外れるPIXの合計 */
+  expiring: number;
+
+  /** 日毎の予測 */
+  days: ForecastDay[];
+
+  /** 予測期間中、現在のPgnLevelを維持するために毎日必要な最小のPIX */
+  required_daily: number;
+}
+
+/**
+ * 1日分の予測
+ */
+export interface ForecastDay {
+  /** 予測する日; この日の前日までの30日間で集計する */
+  date: Date;
+
+  /** この日に集計期間から外れるPIX */
+  expiring: number;
+
+  /** 集計期間のPIX */
+  window_pix: number;
+
+  /** 予測されるPgnLevel */
+  level: PgnLevel;
+}
+
+/**
+ * PgnLevelの閾値のシーズン
+ */
+export interface Season {
+  id: number;
+  /** シーズン名 */
+  name: string;
+  /** 有効になる日 */
+  effective_from: Date;
+  bronze_min: number;
+  silver_min: number;
+  gold_min: number;
+  platinum_min: number;
+  diamond_min: number;
+  master_min: number;
+  grandmaster_min: number;
+}
+
+/**
+ * PIXの取得元の内訳
+ */
+export interface PixSources {
+  /** 集計期間の初日 */
+  start_date: Date;
+
+  /** 集計期間の最終日 */
+  end_date: Date;
+
+  /** PgritにおけるPIX */
+  pgrit: number;
+
+  /** DawnにおけるPIX */
+  dawn: number;
+
+  /** その他のPIX */
+  other: number;
 }
 
 /**
@@ -133,4 +213,77 @@ export default interface UserProfile {
 
   /** PGN情報 */
   pgn: PgnInfo;
+
+  /** 個人の目標と進捗 */
+  goals: GoalProgress[];
+
+  /** 得た実績 */
+  achievements: Achievement[];
+}
+
+/**
+ * PIXの履歴から判定した実績
+ */
+export interface Achievement {
+  user_id: string;
+
+  /** 実績の種類 */
+  kind: "streak" | "first_level" | "best_day" | "total_pix";
+
+  /** 種類の中での段階; 連続日数や到達したPgnLevelなど */
+  tier: string;
+
+  /** 実績を得た時点の記録 */
+  value: number;
+
+  /** 実績を得た日 */
+  earned_on: Date;
+}
+
+/**
+ * 個人の目標
+ */
+export interface Goal {
+  id: number;
+
+  /** 目標とするPgnLevel; target_pixとどちらか一方を指定する */
+  target_level?: PgnLevel;
+
+  /** 目標とする月間PIX; target_levelとどちらか一方を指定する */
+  target_pix?: number;
+
+  /** 期限; この日のPgnLevelで達成を判定する */
+  deadline: Date;
+
+  /** 作成日時 */
+  created_at: string;
+}
+
+/**
+ * 個人の目標の進捗
+ */
+export interface GoalProgress {
+  /** 目標 */
+  goal: Goal;
+
+  /** 目標の月間PIX; PgnLevelが目標の場合は期限日に有効な閾値 */
+  target_pix: number;
+
+  /** 最近1ヶ月のPIX */
+  current_pix: number;
+
+  /** 目標に対する進捗 */
+  progress: number;
+
+  /** 目標までに必要な残りのPIX */
+  remaining: number;
+
+  /** 期限までの残り日数 */
+  days_left: number;
+
+  /** 期限に目標へ到達するために毎日必要な最小のPIX; 期限を過ぎている場合はundefined */
+  required_daily?: number;
+
+  /** 目標の作成後に初めてPgnLevel(月間PIX)が目標に到達した日; 未達成の場合はundefined */
+  achieved_on?: Date;
 }
@@ -1,5 +1,5 @@
 use entity::record::Record;
-use serde_json::from_str;
+use serde_json::{from_str, Value};
 
 fn main() {
     let args = std::env::args().collect::<Vec<String>>();
@@ -8,6 +8,21 @@ fn main() {
         std::process::exit(1);
     }
     let file = std::fs::read_to_string(&args[1]).unwrap();
-    let records: Vec<Record> = from_str(&file).unwrap();
-    println!("{} records loaded.", records.len());
+    let values: Vec<Value> = from_str(&file).unwrap();
+    let total = values.len();
+    let mut loaded = 0;
+    for (index, value) in values.into_iter().enumerate() {
+        match Record::parse_checked(value) {
+            Ok(_) => loaded += 1,
+            Err(errors) => {
+                for error in errors {
+                    eprintln!(
+                        "#{}: {}: {} ({})",
+                        index, error.field, error.message, error.value
+                    );
+                }
+            }
+        }
+    }
+    println!("{} of {} records loaded.", loaded, total);
 }
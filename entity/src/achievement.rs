@@ -0,0 +1,41 @@
+//! PIXの履歴から判定したユーザの実績: 連続記録や初めて到達したPgnLevel、自己ベストなど。
+//! 種類・ユーザ・段階の組で一意になり、得た日を保持する
+
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "achievements")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    /// Ethereumのウォレットアドレス
+    pub user_id: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    /// 実績の種類
+    pub kind: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    /// 種類の中での段階; 連続日数や到達したPgnLevelなど
+    pub tier: String,
+    /// 実績を得た時点の記録
+    pub value: i32,
+    /// 実績を得た日
+    pub earned_on: Date,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,47 @@
+use sea_orm::prelude::DateTimeUtc;
+use serde::Serialize;
+
+/// 加入月毎のコホート分析
+#[derive(Debug, Clone, Serialize)]
+pub struct CohortReport {
+    /// PIXデータの更新日時
+    pub updated_at: DateTimeUtc,
+
+    /// 加入月の昇順のコホート
+    pub cohorts: Vec<Cohort>,
+}
+
+/// 同じ月に加入したメンバー
+#[derive(Debug, Clone, Serialize)]
+pub struct Cohort {
+    /// 加入月; YYYY-MM
+    pub join_month: String,
+
+    /// メンバー数
+    pub members: u64,
+
+    /// 在籍中(アクティブかつ脱退日がない)のメンバー数
+    pub retained: u64,
+
+    /// 在籍率
+    pub retention: f64,
+
+    /// 加入月からの経過月毎のPIX; 今月は途中までの集計
+    pub months: Vec<CohortMonth>,
+}
+
+/// 加入月からある月数が経過した月のPIX
+#[derive(Debug, Clone, Serialize)]
+pub struct CohortMonth {
+    /// 加入月を0とした経過月数
+    pub offset: u32,
+
+    /// 対象の月; YYYY-MM
+    pub month: String,
+
+    /// PIXの合計
+    pub total_pix: u64,
+
+    /// メンバー1人あたりのPIX; 脱退したメンバーも含めて平均する
+    pub average_pix: f64,
+}
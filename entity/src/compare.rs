@@ -0,0 +1,66 @@
+use crate::pgn_level::PgnLevel;
+
+use super::user::Model as User;
+use chrono::NaiveDate;
+use sea_orm::prelude::DateTimeUtc;
+use serde::Serialize;
+use serde_with::serde_as;
+
+/// 複数のユーザの比較
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    /// PIXデータの更新日時
+    pub updated_at: DateTimeUtc,
+
+    /// 日毎のPIXの日付; 昇順
+    pub dates: Vec<NaiveDate>,
+
+    /// 指定された順のユーザ
+    pub users: Vec<ComparedUser>,
+
+    /// ユーザの組毎の差
+    pub head_to_head: Vec<HeadToHead>,
+}
+
+/// 比較するユーザ1人分
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparedUser {
+    /// ユーザ情報
+    pub user: User,
+
+    /// datesに対応する日毎のPIX
+    pub daily: Vec<u32>,
+
+    /// 最近1ヶ月のPIX
+    pub last_month: u32,
+
+    /// 現在のPgnLevel
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub level: PgnLevel,
+
+    /// 比較するユーザの中での順位; 同じPIXの場合は同順位。ランキングの順位とは異なる
+    pub rank_in_comparison: u64,
+}
+
+/// 2人のユーザの差; aからbを引いた値
+#[derive(Debug, Clone, Serialize)]
+pub struct HeadToHead {
+    /// Pgrit ID
+    pub a: String,
+
+    /// Pgrit ID
+    pub b: String,
+
+    /// 最近1ヶ月のPIXの差
+    pub pix_diff: i64,
+
+    /// PgnLevelの段階の差
+    pub level_diff: i32,
+
+    /// aがbより多くPIXを得た日数
+    pub days_ahead: u32,
+
+    /// aがbより少なくPIXを得た日数
+    pub days_behind: u32,
+}
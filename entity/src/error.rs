@@ -8,6 +8,10 @@ pub enum Error {
     Reqwest(#[from] reqwest::Error),
     #[error("Invalid date range")]
     InvalidDateRange,
+    #[error("Missing OAuth scope: {0}")]
+    MissingScope(&'static str),
+    #[error("Invalid goal: {0}")]
+    InvalidGoal(&'static str),
     #[error("Serde error: {0}")]
     Serde(#[from] serde_json::Error),
     #[error("Other error: {0}")]
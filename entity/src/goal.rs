@@ -0,0 +1,42 @@
+//! ユーザが設定した個人の目標: 期限までに目標のPgnLevelか月間PIXに到達することを目指す
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "goals")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    /// Ethereumのウォレットアドレス
+    #[serde(skip_deserializing)]
+    pub user_id: String,
+    /// 目標とするPgnLevel; target_pixとどちらか一方を指定する
+    pub target_level: Option<String>,
+    /// 目標とする月間PIX; target_levelとどちらか一方を指定する
+    pub target_pix: Option<i32>,
+    /// 期限; この日のPgnLevelで達成を判定する
+    pub deadline: Date,
+    /// 作成日時
+    #[serde(skip_deserializing)]
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,67 @@
+use crate::pgn_level::PgnLevel;
+
+use chrono::NaiveDate;
+use sea_orm::prelude::DateTimeUtc;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+/// 集計に用いる学生情報の項目
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    Office,
+    University,
+    Course,
+    Major,
+    Level,
+    DegreeStep,
+}
+
+/// 学生情報の項目毎の直近30日のPIXの集計
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupStats {
+    /// PIXデータの更新日時
+    pub updated_at: DateTimeUtc,
+
+    /// 集計に用いた項目
+    pub by: GroupBy,
+
+    /// 集計期間の初日
+    pub start_date: NaiveDate,
+
+    /// 集計期間の最終日
+    pub end_date: NaiveDate,
+
+    /// メンバー数の降順のグループ
+    pub groups: Vec<GroupStat>,
+}
+
+/// 1グループの集計
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupStat {
+    /// グループ名; 項目の値
+    pub group: String,
+
+    /// メンバー数
+    pub members: u64,
+
+    /// 30日間のPIXの合計
+    pub total_pix: u64,
+
+    /// メンバー1人あたりの30日間のPIXの中央値
+    pub median_pix: f64,
+
+    /// PgnLevel毎の人数; Ironから昇順
+    pub levels: Vec<LevelCount>,
+}
+
+/// あるPgnLevelの人数
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelCount {
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub level: PgnLevel,
+
+    /// 人数
+    pub members: u64,
+}
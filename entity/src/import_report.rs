@@ -0,0 +1,38 @@
+use serde::Serialize;
+
+use crate::record::FieldError;
+
+/// 取得元APIのレスポンスを取り込んだ結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    /// レコードの総数
+    pub total: usize,
+
+    /// 取り込んだレコードの数
+    pub imported: usize,
+
+    /// upsertしたPIXの行数
+    pub rows_upserted: usize,
+
+    /// 取り込めなかったレコード
+    pub failed: Vec<RecordReport>,
+}
+
+/// 問題のあったレコード
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordReport {
+    /// ファイル中の位置(0始まり)
+    pub index: usize,
+
+    /// Pgrit ID
+    pub id: Option<String>,
+
+    /// Ethereumのウォレットアドレス
+    pub wallet_address: Option<String>,
+
+    /// 問題のあったフィールド
+    pub errors: Vec<FieldError>,
+
+    /// レコード自体を読み飛ばしたか; falseの場合は問題のあったフィールドのみを除いて取り込んだ
+    pub skipped: bool,
+}
@@ -0,0 +1,47 @@
+use crate::pgn_level::PgnLevel;
+
+use super::student::Model as Student;
+use super::user::Model as User;
+use sea_orm::prelude::DateTimeUtc;
+use serde::Serialize;
+use serde_with::serde_as;
+
+/// 直近30日のPIXによるランキング
+#[derive(Debug, Clone, Serialize)]
+pub struct Leaderboard {
+    /// PIXデータの更新日時
+    pub updated_at: DateTimeUtc,
+
+    /// 絞り込み後の全体の人数
+    pub total: u64,
+
+    /// ページ番号(1始まり)
+    pub page: u64,
+
+    /// 1ページあたりの人数
+    pub per_page: u64,
+
+    /// 順位順のエントリ
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+/// ランキングの1行
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaderboardEntry {
+    /// 順位; 同じPIXの場合は同順位
+    pub rank: u64,
+
+    /// ユーザ情報
+    pub user: User,
+
+    /// 学生情報
+    pub student: Option<Student>,
+
+    /// 最近1ヶ月のPIX
+    pub last_month: u32,
+
+    /// 現在のPgnLevel
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub level: PgnLevel,
+}
@@ -0,0 +1,46 @@
+use crate::{group_stats::LevelCount, level::Level};
+
+use chrono::NaiveDate;
+use sea_orm::prelude::DateTimeUtc;
+use serde::Serialize;
+use serde_with::serde_as;
+
+/// ある日のアクティブなユーザのPgnLevelの分布
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelStats {
+    /// PIXデータの更新日時
+    pub updated_at: DateTimeUtc,
+
+    /// 対象の日
+    pub date: NaiveDate,
+
+    /// 集計期間の初日
+    pub start_date: NaiveDate,
+
+    /// 集計期間の最終日
+    pub end_date: NaiveDate,
+
+    /// アクティブなユーザ数
+    pub members: u64,
+
+    /// PgnLevel毎の人数; Ironから昇順
+    pub levels: Vec<LevelCount>,
+
+    /// 学生レベル毎のPgnLevelの分布
+    pub by_student_level: Vec<StudentLevelStats>,
+}
+
+/// ある学生レベルのユーザのPgnLevelの分布
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct StudentLevelStats {
+    /// 学生レベル; 学生情報がないユーザはnull
+    #[serde_as(as = "Option<serde_with::DisplayFromStr>")]
+    pub student_level: Option<Level>,
+
+    /// ユーザ数
+    pub members: u64,
+
+    /// PgnLevel毎の人数; Ironから昇順
+    pub levels: Vec<LevelCount>,
+}
@@ -0,0 +1,54 @@
+use crate::pgn_level::PgnLevel;
+
+use super::user::Model as User;
+use chrono::NaiveDate;
+use sea_orm::prelude::DateTimeUtc;
+use serde::Serialize;
+use serde_with::serde_as;
+
+/// ユーザのPgnLevelの推移
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelTimeline {
+    /// ユーザ情報
+    pub user: User,
+
+    /// PIXデータの更新日時
+    pub updated_at: DateTimeUtc,
+
+    /// 1日ごとのPgnLevel
+    pub days: Vec<LevelPoint>,
+
+    /// PgnLevelが変化した日
+    pub changes: Vec<LevelChange>,
+}
+
+/// ある日のPgnLevel
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelPoint {
+    /// 日付
+    pub date: NaiveDate,
+
+    /// 前日までの30日間のPIX
+    pub window_pix: u32,
+
+    /// その日のPgnLevel; 前日までの30日間のPIXで決まる
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub level: PgnLevel,
+}
+
+/// PgnLevelの昇格・降格
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelChange {
+    /// 変化した日
+    pub date: NaiveDate,
+
+    /// 変化前のPgnLevel
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub from: PgnLevel,
+
+    /// 変化後のPgnLevel
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub to: PgnLevel,
+}
@@ -1,13 +1,30 @@
+pub mod achievement;
+pub mod announcement;
+pub mod cohort;
+pub mod compare;
 pub mod degree;
 pub mod error;
+pub mod goal;
 pub mod grade;
+pub mod group_stats;
+pub mod import_report;
+pub mod leaderboard;
 pub mod level;
+pub mod level_stats;
+pub mod level_timeline;
 pub mod mstdn_token;
+pub mod notification;
 pub mod pgn_level;
 pub mod pix;
+pub mod pix_source;
+pub mod public_profile;
 pub mod record;
+pub mod refresh_job;
+pub mod refresh_status;
 pub mod refreshed_users;
+pub mod season;
 pub mod sex;
 pub mod student;
 pub mod user;
+pub mod user_level;
 pub mod user_profile;
@@ -13,6 +13,18 @@ pub struct Model {
     pub authorization_code: String,
     /// Mastodonトークン
     pub access_token: String,
+    /// トークンに許可されたスコープ(空白区切り); 記録する前に発行されたトークンはNone
+    pub scope: Option<String>,
+}
+
+impl Model {
+    /// このトークンで投稿できるか
+    pub fn can_post(&self) -> bool {
+        self.scope.as_deref().is_some_and(|s| {
+            s.split_whitespace()
+                .any(|s| s == "write" || s == "write:statuses")
+        })
+    }
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,26 @@
+//! 送信した通知の記録: 同じ通知を重複して送らないために用いる。
+//! 通知の種類・ユーザ・対象日の組で一意になる
+
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "notifications")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    /// 通知の種類
+    pub kind: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    /// Ethereumのウォレットアドレス; ユーザに紐付かない通知の場合は空文字列
+    pub user_id: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    /// 通知の対象日
+    pub date: Date,
+    /// 送信日時
+    pub sent_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -6,9 +6,10 @@ use std::{
 
 use num_derive::FromPrimitive;
 use saturating_cast::SaturatingCast;
+use serde::Serialize;
 
 /// Pgn上でのレベルを表す
-#[derive(FromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(FromPrimitive, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(i8)]
 pub enum PgnLevel {
     Iron = 0,
@@ -64,7 +65,7 @@ fn cast(step: i8) -> PgnLevel {
     match step {
         step if step < PgnLevel::Iron as i8 => PgnLevel::Iron,
         step if step > PgnLevel::GrandMaster as i8 => PgnLevel::GrandMaster,
-        step => unsafe { std::mem::transmute(step) },
+        step => unsafe { std::mem::transmute::<i8, PgnLevel>(step) },
     }
 }
 
@@ -95,21 +96,13 @@ impl<T: SaturatingCast + saturating_cast::SaturatingElement<i8>> SubAssign<T> fo
 }
 
 impl PgnLevel {
+    /// 既定の閾値における、このレベルに必要な月間PIX
     pub fn min_pix(self) -> u32 {
-        use max_values::*;
-        match self {
-            PgnLevel::Iron => 0,
-            PgnLevel::Bronze => BRONZE_MIN,
-            PgnLevel::Silver => SILVER_MIN,
-            PgnLevel::Gold => GOLD_MIN,
-            PgnLevel::Platinum => PLATINUM_MIN,
-            PgnLevel::Diamond => DIAMAND_MIN,
-            PgnLevel::Master => MASTER_MIN,
-            PgnLevel::GrandMaster => GRANDMASTER_MIN,
-        }
+        Thresholds::default().min_pix(self)
     }
 }
 
+/// 既定の閾値
 pub mod max_values {
     pub const BRONZE_MIN: u32 = 500;
     pub const SILVER_MIN: u32 = 1000;
@@ -121,17 +114,65 @@ pub mod max_values {
 }
 
 impl From<u32> for PgnLevel {
+    /// 既定の閾値で月間PIXからレベルを求める
     fn from(pix_monthly: u32) -> Self {
+        Thresholds::default().level(pix_monthly)
+    }
+}
+
+/// 各PgnLevelに必要な月間PIXの閾値
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Thresholds {
+    pub bronze_min: u32,
+    pub silver_min: u32,
+    pub gold_min: u32,
+    pub platinum_min: u32,
+    pub diamond_min: u32,
+    pub master_min: u32,
+    pub grandmaster_min: u32,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
         use max_values::*;
+        Thresholds {
+            bronze_min: BRONZE_MIN,
+            silver_min: SILVER_MIN,
+            gold_min: GOLD_MIN,
+            platinum_min: PLATINUM_MIN,
+            diamond_min: DIAMAND_MIN,
+            master_min: MASTER_MIN,
+            grandmaster_min: GRANDMASTER_MIN,
+        }
+    }
+}
+
+impl Thresholds {
+    /// このレベルに必要な月間PIX
+    pub fn min_pix(&self, level: PgnLevel) -> u32 {
+        match level {
+            PgnLevel::Iron => 0,
+            PgnLevel::Bronze => self.bronze_min,
+            PgnLevel::Silver => self.silver_min,
+            PgnLevel::Gold => self.gold_min,
+            PgnLevel::Platinum => self.platinum_min,
+            PgnLevel::Diamond => self.diamond_min,
+            PgnLevel::Master => self.master_min,
+            PgnLevel::GrandMaster => self.grandmaster_min,
+        }
+    }
+
+    /// 月間PIXからレベルを求める
+    pub fn level(&self, pix_monthly: u32) -> PgnLevel {
         use PgnLevel::*;
         match pix_monthly {
-            pix if pix < BRONZE_MIN => Iron,
-            pix if pix < SILVER_MIN => Bronze,
-            pix if pix < GOLD_MIN => Silver,
-            pix if pix < PLATINUM_MIN => Gold,
-            pix if pix < DIAMAND_MIN => Platinum,
-            pix if pix < MASTER_MIN => Diamond,
-            pix if pix < GRANDMASTER_MIN => Master,
+            pix if pix < self.bronze_min => Iron,
+            pix if pix < self.silver_min => Bronze,
+            pix if pix < self.gold_min => Silver,
+            pix if pix < self.platinum_min => Gold,
+            pix if pix < self.diamond_min => Platinum,
+            pix if pix < self.master_min => Diamond,
+            pix if pix < self.grandmaster_min => Master,
             _ => GrandMaster,
         }
     }
@@ -10,7 +10,7 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub user_id: String,
 
-    pub amount: u32,
+    pub amount: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,43 @@
+//! リフレッシュ毎・ユーザー毎のPIX取得元の内訳を管理するテーブル: ulidが1回のリフレッシュに対応する。
+//! 取得期間(start_date〜end_date)におけるPgrit, Dawn, その他それぞれの合計を記録する
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "pix_sources")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub ulid: String,
+    #[sea_orm(primary_key)]
+    pub user_id: String,
+
+    /// 集計期間の初日
+    pub start_date: Date,
+    /// 集計期間の最終日
+    pub end_date: Date,
+
+    /// PgritにおけるPIX
+    pub pgrit: i32,
+    /// DawnにおけるPIX
+    pub dawn: i32,
+    /// その他のPIX
+    pub other: i32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,7 +1,8 @@
 use chrono::{DateTime, NaiveDate};
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
 use serde_with::{serde_as, DisplayFromStr, NoneAsEmptyString};
-use std::collections::HashMap;
+use std::{collections::HashMap, str::FromStr};
 
 use crate::{grade::Grade, level::Level, sex::Sex};
 
@@ -99,34 +100,31 @@ pub struct Record {
     pub daily_totals: HashMap<NaiveDate, u32>,
 }
 
+/// 日付の文字列を読み込む; RFC3339またはYYYY-MM-DDで始まる文字列に対応
+fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(s) {
+        return Ok(date.with_timezone(&chrono::Local).date_naive());
+    }
+    let res = s.len() >= 10
+        && s.get(4..5) == Some("-")
+        && s.get(7..8) == Some("-")
+        && [s.get(0..4), s.get(5..7), s.get(8..10)]
+            .iter()
+            .all(|s| s.is_some_and(|s| s.chars().all(|c| c.is_ascii_digit())));
+    if res {
+        NaiveDate::parse_from_str(&s[0..10], "%Y-%m-%d").map_err(|e| e.to_string())
+    } else {
+        Err("invalid date format".to_string())
+    }
+}
+
 fn deserialize_optional_naive_date<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
 where
     D: Deserializer<'de>,
 {
     let s: Result<Option<String>, _> = Option::deserialize(deserializer);
     match s? {
-        Some(s) if !s.is_empty() => {
-            if let Ok(date) = DateTime::parse_from_rfc3339(&s) {
-                return Ok(Some(date.with_timezone(&chrono::Local).date_naive()));
-            }
-            let res = {
-                let s = s.as_str();
-                s.len() >= 10
-                    && &s[4..5] == "-"
-                    && &s[7..8] == "-"
-                    && [&s[0..4], &s[5..7], &s[8..10]]
-                        .iter()
-                        .all(|s| s.chars().all(|c| c.is_ascii_digit()))
-            };
-            if res {
-                Ok(Some(
-                    NaiveDate::parse_from_str(&s[0..10], "%Y-%m-%d")
-                        .map_err(serde::de::Error::custom)?,
-                ))
-            } else {
-                Err(serde::de::Error::custom("invalid date format"))
-            }
-        }
+        Some(s) if !s.is_empty() => parse_date(&s).map(Some).map_err(serde::de::Error::custom),
         _ => Ok(None),
     }
 }
@@ -144,3 +142,301 @@ where
         })
         .collect()
 }
+
+/// Recordのフィールド名(日毎のPIX以外)
+const FIELDS: &[&str] = &[
+    "id",
+    "walletAddress",
+    "grade",
+    "course",
+    "level",
+    "sex",
+    "joinDate",
+    "joinMonth",
+    "office",
+    "email",
+    "emailOf4nonome",
+    "university",
+    "major",
+    "leaveDate",
+    "active",
+    "slackId",
+    "discordId",
+    "total",
+    "total_pgrit",
+    "total_dawn",
+    "total_other",
+];
+
+/// レコードのフィールドの検証エラー
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    /// フィールド名; 日毎のPIXの場合は日付のキー
+    pub field: String,
+    /// 問題のあった値
+    pub value: Value,
+    /// エラー内容
+    pub message: String,
+}
+
+/// 日毎のPIX以外のフィールドのうち、値が不正な場合に読み飛ばせるもの
+const OPTIONAL_FIELDS: &[&str] = &["grade", "level", "sex", "joinDate", "leaveDate"];
+
+/// 列挙型・日付・日毎のPIXの値を検証し、問題のあったフィールドを返す
+fn check_fields(map: &Map<String, Value>) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let mut check = |field: &str, result: Result<(), String>| {
+        if let Err(message) = result {
+            errors.push(FieldError {
+                field: field.to_string(),
+                value: map.get(field).cloned().unwrap_or(Value::Null),
+                message,
+            });
+        }
+    };
+    let str_field = |field: &str| map.get(field).and_then(Value::as_str);
+
+    if let Some(s) = str_field("grade") {
+        check("grade", Grade::from_str(s).map(|_| ()));
+    }
+    if let Some(s) = str_field("level") {
+        check("level", Level::from_str(s).map(|_| ()));
+    }
+    if let Some(s) = str_field("sex") {
+        check("sex", Sex::from_str(s).map(|_| ()));
+    }
+    for field in ["joinDate", "leaveDate"] {
+        if let Some(s) = str_field(field).filter(|s| !s.is_empty()) {
+            check(field, parse_date(s).map(|_| ()));
+        }
+    }
+    for (key, amount) in map.iter().filter(|(k, _)| !FIELDS.contains(&k.as_str())) {
+        check(
+            key,
+            NaiveDate::parse_from_str(key, "%Y-%m-%d")
+                .map_err(|e| format!("invalid date key: {}", e))
+                .and_then(|_| match amount.as_u64() {
+                    Some(a) if a <= u32::MAX as u64 => Ok(()),
+                    _ => Err("invalid amount".to_string()),
+                }),
+        );
+    }
+    errors
+}
+
+/// 型の不一致や必須フィールドの欠落を含めてレコードを読み込む
+fn deserialize_checked(map: Map<String, Value>) -> Result<Record, FieldError> {
+    let value = Value::Object(map);
+    serde_path_to_error::deserialize(&value).map_err(|e| {
+        // 必須フィールドの欠落のようにレコード全体に関するエラーはフィールド名を空にする
+        let field = match e.path().to_string() {
+            root if root == "." => String::new(),
+            field => field,
+        };
+        let value = value.get(&field).cloned().unwrap_or(Value::Null);
+        FieldError {
+            field,
+            value,
+            message: e.into_inner().to_string(),
+        }
+    })
+}
+
+fn not_an_object(value: Value) -> Vec<FieldError> {
+    vec![FieldError {
+        field: String::new(),
+        value,
+        message: "record is not an object".to_string(),
+    }]
+}
+
+impl Record {
+    /// JSONの値を1件のレコードとして検証しながら読み込む。
+    /// 失敗した場合は問題のあった全てのフィールドを返す
+    pub fn parse_checked(value: Value) -> Result<Record, Vec<FieldError>> {
+        let Value::Object(map) = value else {
+            return Err(not_an_object(value));
+        };
+        let errors = check_fields(&map);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        deserialize_checked(map).map_err(|e| vec![e])
+    }
+
+    /// JSONの値を1件のレコードとして読み込む。
+    /// 学年・レベル・性別・日付が不正な場合は`None`として、日毎のPIXが不正な場合はその日を除いて読み込み、
+    /// 読み飛ばしたフィールドを警告として返す。
+    /// 必須フィールドが欠けているなど、レコードとして扱えない場合のみ失敗する
+    pub fn parse_lenient(value: Value) -> Result<(Record, Vec<FieldError>), Vec<FieldError>> {
+        let Value::Object(mut map) = value else {
+            return Err(not_an_object(value));
+        };
+        let mut warnings = check_fields(&map);
+        for warning in &warnings {
+            if OPTIONAL_FIELDS.contains(&warning.field.as_str()) {
+                map.insert(warning.field.clone(), Value::Null);
+            } else {
+                map.remove(&warning.field);
+            }
+        }
+        match deserialize_checked(map) {
+            Ok(record) => Ok((record, warnings)),
+            Err(e) => {
+                warnings.push(e);
+                Err(warnings)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn record() -> Value {
+        json!({
+            "id": "alice",
+            "walletAddress": "0xa",
+            "grade": "B3",
+            "course": "AI",
+            "level": "新人",
+            "sex": "男性",
+            "joinDate": "2024-01-15",
+            "joinMonth": "2024/01",
+            "office": "Tokyo",
+            "email": "alice@example.com",
+            "emailOf4nonome": "alice@4nonome.example.com",
+            "university": "UTokyo",
+            "major": "CS",
+            "leaveDate": "",
+            "active": true,
+            "slackId": "U1",
+            "discordId": "",
+            "total": 30,
+            "total_pgrit": 20,
+            "total_dawn": 5,
+            "total_other": 5,
+            "2024-03-01": 10,
+            "2024-03-02": 20,
+        })
+    }
+
+    fn with(field: &str, value: Value) -> Value {
+        let mut record = record();
+        record[field] = value;
+        record
+    }
+
+    fn fields(errors: &[FieldError]) -> Vec<&str> {
+        errors.iter().map(|e| e.field.as_str()).collect()
+    }
+
+    #[test]
+    fn parse_date_formats() {
+        assert_eq!(parse_date("2024-03-15"), Ok(date("2024-03-15")));
+        // YYYY-MM-DDで始まれば残りは無視する
+        assert_eq!(parse_date("2024-03-15T09:00:00"), Ok(date("2024-03-15")));
+        assert_eq!(parse_date("2024-03-15 (金)"), Ok(date("2024-03-15")));
+        assert_eq!(
+            parse_date("2024-03-15T12:00:00+00:00"),
+            Ok(date("2024-03-15"))
+        );
+    }
+
+    #[test]
+    fn parse_date_rejects_malformed() {
+        for s in [
+            "",
+            "2024-03-1",
+            "2024/03/15",
+            "24-03-15",
+            "2024-3-15x",
+            "2024-13-01",
+            "2024-02-30",
+            "２０２４-03-15",
+            // 文字の境界が10バイト目に無い
+            "2024-03-1日",
+        ] {
+            assert!(parse_date(s).is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn parse_checked_valid_record() {
+        let record = Record::parse_checked(record()).unwrap();
+        assert_eq!(record.wallet_address, "0xa");
+        assert_eq!(record.level, Some(Level::Newbie));
+        assert_eq!(record.join_date, Some(date("2024-01-15")));
+        assert_eq!(record.leave_date, None);
+        assert_eq!(record.discord_id, None);
+        assert_eq!(
+            record.daily_totals,
+            HashMap::from([(date("2024-03-01"), 10), (date("2024-03-02"), 20)])
+        );
+    }
+
+    #[test]
+    fn parse_checked_reports_every_invalid_field() {
+        let mut value = with("grade", json!("???"));
+        value["level"] = json!("謎");
+        value["joinDate"] = json!("2024/01/15");
+        value["2024-02-30"] = json!(1);
+        value["2024-03-03"] = json!(-1);
+        let errors = Record::parse_checked(value).unwrap_err();
+
+        let mut fields = fields(&errors);
+        fields.sort();
+        assert_eq!(
+            fields,
+            ["2024-02-30", "2024-03-03", "grade", "joinDate", "level"]
+        );
+        assert_eq!(errors[0].value, json!("???"));
+    }
+
+    #[test]
+    fn parse_lenient_skips_invalid_fields() {
+        let mut value = with("grade", json!("???"));
+        value["sex"] = json!("?");
+        value["leaveDate"] = json!("someday");
+        value["2024-03-03"] = json!("many");
+        value["2024-13-01"] = json!(5);
+        let (record, warnings) = Record::parse_lenient(value).unwrap();
+
+        assert!(record.grade.is_none());
+        assert_eq!(record.sex, None);
+        assert_eq!(record.leave_date, None);
+        assert_eq!(record.daily_totals.len(), 2);
+        let mut fields = fields(&warnings);
+        fields.sort();
+        assert_eq!(
+            fields,
+            ["2024-03-03", "2024-13-01", "grade", "leaveDate", "sex"]
+        );
+    }
+
+    #[test]
+    fn parse_lenient_fails_on_required_fields() {
+        let mut value = record();
+        value.as_object_mut().unwrap().remove("walletAddress");
+        let errors = Record::parse_lenient(value.clone()).unwrap_err();
+        assert_eq!(fields(&errors), [""]);
+        assert!(errors[0].message.contains("walletAddress"));
+        assert!(Record::parse_checked(value).is_err());
+
+        let errors = Record::parse_lenient(with("total", json!("30"))).unwrap_err();
+        assert_eq!(fields(&errors), ["total"]);
+        // 文字列でない値は読み飛ばせない
+        let errors = Record::parse_lenient(with("sex", json!(1))).unwrap_err();
+        assert_eq!(fields(&errors), ["sex"]);
+
+        let errors = Record::parse_lenient(json!(["alice"])).unwrap_err();
+        assert_eq!(errors[0].message, "record is not an object");
+    }
+}
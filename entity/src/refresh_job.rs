@@ -0,0 +1,39 @@
+//! リフレッシュの実行履歴を記録するテーブル: ulidはリフレッシュの開始日時に対応する。
+//! データが更新されなくなった原因を追えるように、取得件数やエラー内容を記録する
+
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+use crate::refresh_status::RefreshStatus;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "refresh_jobs")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub ulid: String,
+    /// 状態
+    pub status: RefreshStatus,
+    /// 開始日時
+    pub started_at: DateTimeUtc,
+    /// 終了日時
+    pub finished_at: Option<DateTimeUtc>,
+    /// 取得を要求した期間の初日
+    pub start_date: Option<Date>,
+    /// 取得を要求した期間の最終日
+    pub end_date: Option<Date>,
+    /// 取得したレコード数
+    pub records_fetched: Option<i32>,
+    /// upsertしたPIXの行数
+    pub rows_upserted: Option<i32>,
+    /// 新しいユーザがいたために30日分を再取得したか
+    pub refetched: bool,
+    /// エラー内容
+    pub error: Option<String>,
+    /// 読み込み時に問題のあったレコード
+    pub warnings: Option<Json>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,37 @@
+use std::fmt::Display;
+
+use sea_orm::{DeriveActiveEnum, EnumIter};
+use serde::Serialize;
+
+/// リフレッシュジョブの状態
+#[derive(PartialEq, Debug, Clone, Copy, EnumIter, DeriveActiveEnum, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(1))")]
+pub enum RefreshStatus {
+    /// 実行中
+    #[sea_orm(string_value = "running")]
+    Running,
+    /// 前回の更新から間隔が短いため中止
+    #[sea_orm(string_value = "skipped")]
+    Skipped,
+    /// 成功
+    #[sea_orm(string_value = "succeeded")]
+    Succeeded,
+    /// 失敗
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+impl Display for RefreshStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                RefreshStatus::Running => "running",
+                RefreshStatus::Skipped => "skipped",
+                RefreshStatus::Succeeded => "succeeded",
+                RefreshStatus::Failed => "failed",
+            }
+        )
+    }
+}
@@ -0,0 +1,61 @@
+//! PgnLevelの閾値のセットを管理するテーブル: effective_fromの日から次のシーズンが始まるまで有効になる。
+//! 閾値を変更する場合は新しいシーズンを追加することで、過去のレベルが書き換わらないようにする
+
+use chrono::NaiveDate;
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+use crate::pgn_level::Thresholds;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "seasons")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    /// シーズン名
+    pub name: String,
+    /// 有効になる日
+    #[sea_orm(unique)]
+    pub effective_from: Date,
+
+    pub bronze_min: i32,
+    pub silver_min: i32,
+    pub gold_min: i32,
+    pub platinum_min: i32,
+    pub diamond_min: i32,
+    pub master_min: i32,
+    pub grandmaster_min: i32,
+}
+
+impl Model {
+    /// このシーズンの閾値
+    pub fn thresholds(&self) -> Thresholds {
+        let pix = |v: i32| v.max(0) as u32;
+        Thresholds {
+            bronze_min: pix(self.bronze_min),
+            silver_min: pix(self.silver_min),
+            gold_min: pix(self.gold_min),
+            platinum_min: pix(self.platinum_min),
+            diamond_min: pix(self.diamond_min),
+            master_min: pix(self.master_min),
+            grandmaster_min: pix(self.grandmaster_min),
+        }
+    }
+}
+
+/// effective_fromの昇順に並んだシーズンから、指定日に有効なシーズンを返す
+pub fn in_force(seasons: &[Model], date: NaiveDate) -> Option<&Model> {
+    seasons.iter().rev().find(|s| s.effective_from <= date)
+}
+
+/// 指定日に有効な閾値を返す。シーズンが登録されていない場合は既定の閾値
+pub fn thresholds_at(seasons: &[Model], date: NaiveDate) -> Thresholds {
+    in_force(seasons, date)
+        .map(Model::thresholds)
+        .unwrap_or_default()
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -14,7 +14,7 @@ pub struct Model {
     /// 遂行中の学位
     pub degree_step: Degree,
     /// 学年
-    pub grade: u16,
+    pub grade: i16,
     /// 受講コース
     pub course: String,
     /// レベル
@@ -23,6 +23,8 @@ pub struct Model {
     pub sex: Sex,
     /// 参加日
     pub join_date: Date,
+    /// 加入月; 取得元の表記のまま保持する
+    pub join_month: Option<String>,
     /// オフィス
     pub office: String,
     /// メールアドレス
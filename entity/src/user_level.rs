@@ -0,0 +1,36 @@
+//! リフレッシュ毎に確認したユーザのPgnLevel: 前回からの変化を検出するために保持する
+
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "user_levels")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    /// Ethereumのウォレットアドレス
+    pub user_id: String,
+    /// PgnLevel
+    pub level: String,
+    /// 確認した時点での最近1ヶ月のPIX
+    pub last_month: i32,
+    /// 最後にレベルが変化した日時
+    pub changed_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::pgn_level::PgnLevel;
+use crate::{achievement, goal, pgn_level::PgnLevel, season};
 
 use super::student::Model as Student;
 use super::user::Model as User;
@@ -23,12 +23,46 @@ pub struct UserProfile {
 
     /// PGN情報
     pub pgn: PgnInfo,
+
+    /// 個人の目標と進捗
+    pub goals: Vec<GoalProgress>,
+
+    /// 得た実績
+    pub achievements: Vec<achievement::Model>,
+}
+
+/// 個人の目標の進捗
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalProgress {
+    /// 目標
+    pub goal: goal::Model,
+
+    /// 目標の月間PIX; PgnLevelが目標の場合は期限日に有効な閾値
+    pub target_pix: u32,
+
+    /// 最近1ヶ月のPIX
+    pub current_pix: u32,
+
+    /// 目標に対する進捗
+    pub progress: f32,
+
+    /// 目標までに必要な残りのPIX
+    pub remaining: u32,
+
+    /// 期限までの残り日数
+    pub days_left: i64,
+
+    /// 期限に目標へ到達するために毎日必要な最小のPIX; 期限を過ぎている場合はNone
+    pub required_daily: Option<u32>,
+
+    /// 目標の作成後に初めてPgnLevel(月間PIX)が目標に到達した日; 未達成の場合はNone
+    pub achieved_on: Option<NaiveDate>,
 }
 
 #[serde_as]
 #[derive(Debug, Clone, Serialize)]
 pub struct PgnInfo {
-    /// PIXデータの更新日時
+    /// PIXデータの更新日時; リフレッシュが一度も行われていない場合は現在時刻
     pub updated_at: DateTimeUtc,
 
     /// 1日ごとのPIX推移
@@ -56,4 +90,63 @@ pub struct PgnInfo {
 
     /// 次のレベルまでに必要な残りのPIX
     pub behind_next: Option<u32>,
+
+    /// 直近のリフレッシュにおけるPIXの取得元の内訳
+    pub sources: Option<PixSources>,
+
+    /// 適用された閾値のシーズン; 未登録の場合は既定の閾値を用いる
+    pub season: Option<season::Model>,
+
+    /// 今後PIXを得なかった場合の予測
+    pub forecast: Forecast,
+}
+
+/// 今後PIXを得なかった場合の予測
+#[derive(Debug, Clone, Serialize)]
+pub struct Forecast {
+    /// 予測期間中に集計期間から外れるPIXの合計
+    pub expiring: u32,
+
+    /// 日毎の予測
+    pub days: Vec<ForecastDay>,
+
+    /// 予測期間中、現在のPgnLevelを維持するために毎日必要な最小のPIX
+    pub required_daily: u32,
+}
+
+/// 1日分の予測
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct ForecastDay {
+    /// 予測する日; この日の前日までの30日間で集計する
+    pub date: NaiveDate,
+
+    /// この日に集計期間から外れるPIX
+    pub expiring: u32,
+
+    /// 集計期間のPIX
+    pub window_pix: u32,
+
+    /// 予測されるPgnLevel
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub level: PgnLevel,
+}
+
+/// PIXの取得元の内訳
+#[derive(Debug, Clone, Serialize)]
+pub struct PixSources {
+    /// 集計期間の初日
+    pub start_date: NaiveDate,
+
+    /// 集計期間の最終日
+    pub end_date: NaiveDate,
+
+    /// PgritにおけるPIX
+    pub pgrit: u32,
+
+    /// DawnにおけるPIX
+    pub dawn: u32,
+
+    /// その他のPIX
+    pub other: u32,
 }
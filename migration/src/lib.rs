@@ -1,12 +1,40 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20240410_000001_create_table;
+mod m20240410_000002_create_table_typed;
+mod m20240501_000001_create_pix_sources;
+mod m20240502_000001_create_refresh_jobs;
+mod m20240503_000001_create_seasons;
+mod m20240504_000001_add_refresh_job_warnings;
+mod m20240505_000001_create_user_levels;
+mod m20240506_000001_create_announcements;
+mod m20240507_000001_create_notifications;
+mod m20240508_000001_create_goals;
+mod m20240509_000001_create_achievements;
+mod m20240510_000001_add_student_join_month;
+mod m20240511_000001_add_mstdn_token_scope;
+mod m20240512_000001_create_public_profiles;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20240410_000001_create_table::Migration)]
+        vec![
+            // 最初のマイグレーションはバックエンドに合わせて型を直したものを用いる
+            Box::new(m20240410_000002_create_table_typed::Migration),
+            Box::new(m20240501_000001_create_pix_sources::Migration),
+            Box::new(m20240502_000001_create_refresh_jobs::Migration),
+            Box::new(m20240503_000001_create_seasons::Migration),
+            Box::new(m20240504_000001_add_refresh_job_warnings::Migration),
+            Box::new(m20240505_000001_create_user_levels::Migration),
+            Box::new(m20240506_000001_create_announcements::Migration),
+            Box::new(m20240507_000001_create_notifications::Migration),
+            Box::new(m20240508_000001_create_goals::Migration),
+            Box::new(m20240509_000001_create_achievements::Migration),
+            Box::new(m20240510_000001_add_student_join_month::Migration),
+            Box::new(m20240511_000001_add_mstdn_token_scope::Migration),
+            Box::new(m20240512_000001_create_public_profiles::Migration),
+        ]
     }
 }
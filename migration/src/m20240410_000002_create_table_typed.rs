@@ -0,0 +1,155 @@
+//! 最初のマイグレーション(m20240410_000001_create_table)の型を直したもの。
+//! 最初のマイグレーションはSQLite向けに書かれており、ulidに型が無いためPostgreSQL・MySQLでは実行できず、
+//! PIXと学年が符号なしのためMySQLではエンティティの型と合わない。
+//! 適用済みのデータベースに影響しないよう最初のマイグレーションは書き換えず、同じ名前で登録して
+//! SQLiteでは最初のマイグレーションをそのまま、それ以外では型を直したテーブルを作る
+
+use entity::{mstdn_token, pix, refreshed_users, student, user};
+use sea_orm_migration::{prelude::*, sea_orm::DatabaseBackend};
+
+use crate::m20240410_000001_create_table;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        m20240410_000001_create_table::Migration.name()
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() == DatabaseBackend::Sqlite {
+            return m20240410_000001_create_table::Migration.up(manager).await;
+        }
+        manager
+            .create_table(
+                Table::create()
+                    .table(user::Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(user::Column::Id).string().primary_key())
+                    .col(
+                        ColumnDef::new(user::Column::PgritId)
+                            .unique_key()
+                            .string()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(student::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(student::Column::UserId)
+                            .string()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(student::Column::DegreeStep)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(student::Column::Grade)
+                            .small_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(student::Column::Course).string().not_null())
+                    .col(ColumnDef::new(student::Column::Level).string().not_null())
+                    .col(ColumnDef::new(student::Column::Sex).string().not_null())
+                    .col(ColumnDef::new(student::Column::JoinDate).date().not_null())
+                    .col(ColumnDef::new(student::Column::Office).string().not_null())
+                    .col(ColumnDef::new(student::Column::Email).string().not_null())
+                    .col(
+                        ColumnDef::new(student::Column::EmailOf4nonome)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(student::Column::University)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(student::Column::Major).string().not_null())
+                    .col(ColumnDef::new(student::Column::LeaveDate).date().null())
+                    .col(ColumnDef::new(student::Column::Active).boolean().not_null())
+                    .col(ColumnDef::new(student::Column::SlackId).string().not_null())
+                    .col(ColumnDef::new(student::Column::DiscordId).string().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(refreshed_users::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(refreshed_users::Column::UserId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(refreshed_users::Column::Ulid)
+                            .string()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(refreshed_users::Column::UserId)
+                            .col(refreshed_users::Column::Ulid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(pix::Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(pix::Column::UserId).string().not_null())
+                    .col(ColumnDef::new(pix::Column::Date).date().not_null())
+                    .col(ColumnDef::new(pix::Column::Amount).integer().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(pix::Column::UserId)
+                            .col(pix::Column::Date),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(mstdn_token::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(mstdn_token::Column::UserId)
+                            .string()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(mstdn_token::Column::AuthorizationCode)
+                            .string()
+                            .unique_key()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(mstdn_token::Column::AccessToken)
+                            .string()
+                            .unique_key()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        m20240410_000001_create_table::Migration.down(manager).await
+    }
+}
@@ -0,0 +1,63 @@
+use entity::pix_source;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(pix_source::Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(pix_source::Column::Ulid).string().not_null())
+                    .col(
+                        ColumnDef::new(pix_source::Column::UserId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(pix_source::Column::StartDate)
+                            .date()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(pix_source::Column::EndDate)
+                            .date()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(pix_source::Column::Pgrit)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(pix_source::Column::Dawn)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(pix_source::Column::Other)
+                            .integer()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(pix_source::Column::Ulid)
+                            .col(pix_source::Column::UserId),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(pix_source::Entity).to_owned())
+            .await?;
+        Ok(())
+    }
+}
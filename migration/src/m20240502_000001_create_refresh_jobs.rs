@@ -0,0 +1,65 @@
+use entity::refresh_job;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(refresh_job::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(refresh_job::Column::Ulid)
+                            .string()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(refresh_job::Column::Status)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(refresh_job::Column::StartedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(refresh_job::Column::FinishedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(refresh_job::Column::StartDate).date().null())
+                    .col(ColumnDef::new(refresh_job::Column::EndDate).date().null())
+                    .col(
+                        ColumnDef::new(refresh_job::Column::RecordsFetched)
+                            .integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(refresh_job::Column::RowsUpserted)
+                            .integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(refresh_job::Column::Refetched)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(refresh_job::Column::Error).text().null())
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(refresh_job::Entity).to_owned())
+            .await?;
+        Ok(())
+    }
+}
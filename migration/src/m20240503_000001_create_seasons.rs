@@ -0,0 +1,103 @@
+use entity::{pgn_level::Thresholds, season};
+use sea_orm_migration::{prelude::*, sea_orm::prelude::Date};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(season::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(season::Column::Id)
+                            .integer()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(season::Column::Name).string().not_null())
+                    .col(
+                        ColumnDef::new(season::Column::EffectiveFrom)
+                            .date()
+                            .unique_key()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(season::Column::BronzeMin)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(season::Column::SilverMin)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(season::Column::GoldMin).integer().not_null())
+                    .col(
+                        ColumnDef::new(season::Column::PlatinumMin)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(season::Column::DiamondMin)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(season::Column::MasterMin)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(season::Column::GrandmasterMin)
+                            .integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // これまでの固定の閾値を最初のシーズンとして登録
+        let thresholds = Thresholds::default();
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(season::Entity)
+                    .columns([
+                        season::Column::Name,
+                        season::Column::EffectiveFrom,
+                        season::Column::BronzeMin,
+                        season::Column::SilverMin,
+                        season::Column::GoldMin,
+                        season::Column::PlatinumMin,
+                        season::Column::DiamondMin,
+                        season::Column::MasterMin,
+                        season::Column::GrandmasterMin,
+                    ])
+                    .values_panic([
+                        "default".into(),
+                        Date::from_ymd_opt(1970, 1, 1).unwrap().into(),
+                        (thresholds.bronze_min as i32).into(),
+                        (thresholds.silver_min as i32).into(),
+                        (thresholds.gold_min as i32).into(),
+                        (thresholds.platinum_min as i32).into(),
+                        (thresholds.diamond_min as i32).into(),
+                        (thresholds.master_min as i32).into(),
+                        (thresholds.grandmaster_min as i32).into(),
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(season::Entity).to_owned())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use entity::refresh_job;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(refresh_job::Entity)
+                    .add_column(ColumnDef::new(refresh_job::Column::Warnings).json().null())
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(refresh_job::Entity)
+                    .drop_column(refresh_job::Column::Warnings)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
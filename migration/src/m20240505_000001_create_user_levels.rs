@@ -0,0 +1,47 @@
+use entity::user_level;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(user_level::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(user_level::Column::UserId)
+                            .string()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(user_level::Column::Level)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(user_level::Column::LastMonth)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(user_level::Column::ChangedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(user_level::Entity).to_owned())
+            .await?;
+        Ok(())
+    }
+}
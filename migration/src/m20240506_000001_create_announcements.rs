@@ -0,0 +1,42 @@
+use entity::announcement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(announcement::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(announcement::Column::UserId)
+                            .string()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(announcement::Column::Enabled)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(announcement::Column::UseOwnToken)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(announcement::Entity).to_owned())
+            .await?;
+        Ok(())
+    }
+}
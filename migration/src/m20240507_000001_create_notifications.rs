@@ -0,0 +1,49 @@
+use entity::notification;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(notification::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(notification::Column::Kind)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(notification::Column::UserId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(notification::Column::Date).date().not_null())
+                    .col(
+                        ColumnDef::new(notification::Column::SentAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(notification::Column::Kind)
+                            .col(notification::Column::UserId)
+                            .col(notification::Column::Date),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(notification::Entity).to_owned())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+use entity::goal;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(goal::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(goal::Column::Id)
+                            .integer()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(goal::Column::UserId).string().not_null())
+                    .col(ColumnDef::new(goal::Column::TargetLevel).string())
+                    .col(ColumnDef::new(goal::Column::TargetPix).integer())
+                    .col(ColumnDef::new(goal::Column::Deadline).date().not_null())
+                    .col(
+                        ColumnDef::new(goal::Column::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(goal::Entity).to_owned())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use entity::achievement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(achievement::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(achievement::Column::UserId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(achievement::Column::Kind)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(achievement::Column::Tier)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(achievement::Column::Value)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(achievement::Column::EarnedOn)
+                            .date()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(achievement::Column::UserId)
+                            .col(achievement::Column::Kind)
+                            .col(achievement::Column::Tier),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(achievement::Entity).to_owned())
+            .await?;
+        Ok(())
+    }
+}
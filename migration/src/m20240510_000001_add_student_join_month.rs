@@ -0,0 +1,32 @@
+use entity::student;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(student::Entity)
+                    .add_column(ColumnDef::new(student::Column::JoinMonth).string().null())
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(student::Entity)
+                    .drop_column(student::Column::JoinMonth)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use entity::mstdn_token;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(mstdn_token::Entity)
+                    .add_column(ColumnDef::new(mstdn_token::Column::Scope).string().null())
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(mstdn_token::Entity)
+                    .drop_column(mstdn_token::Column::Scope)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use entity::public_profile;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(public_profile::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(public_profile::Column::UserId)
+                            .string()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(public_profile::Column::Enabled)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(public_profile::Entity).to_owned())
+            .await?;
+        Ok(())
+    }
+}
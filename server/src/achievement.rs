@@ -0,0 +1,310 @@
+//! PIXの履歴から実績を判定するルール。
+//! 判定は書き込まれた日から`LOOKBACK_DAYS`日遡った範囲の履歴で行い、それより前は累計と最高値のみを用いる。
+//! それより前に得た実績は保存済みなので、範囲の始めで再び判定された実績は保存時に無視される。
+//! 新しい実績は`Rule`を実装して`RULES`に加える
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use entity::{level_timeline::LevelPoint, pgn_level::PgnLevel};
+
+/// 書き込まれた日より前に読み込む履歴の日数; 連続記録の最長の段階の日数
+pub const LOOKBACK_DAYS: i64 = Streak::TIERS[Streak::TIERS.len() - 1] as i64;
+
+/// 判定に用いるユーザの履歴
+pub struct History<'a> {
+    /// 日毎のPIX
+    pub daily: &'a BTreeMap<NaiveDate, u32>,
+    /// 日毎のPgnLevel
+    pub levels: &'a [LevelPoint],
+    /// `daily`より前の累計PIX
+    pub total_before: u32,
+    /// `daily`より前の1日の最高PIX
+    pub best_before: u32,
+}
+
+/// 判定された実績
+pub struct Award {
+    pub kind: &'static str,
+    pub tier: String,
+    pub value: u32,
+    pub earned_on: NaiveDate,
+}
+
+/// 実績の判定ルール
+pub trait Rule: Sync {
+    /// 実績の種類
+    fn kind(&self) -> &'static str;
+
+    /// 記録を更新していく実績か; falseの場合は一度得た実績を書き換えない
+    fn replaceable(&self) -> bool {
+        false
+    }
+
+    fn evaluate(&self, history: &History) -> Vec<Award>;
+}
+
+/// 判定するルールの一覧
+pub const RULES: &[&dyn Rule] = &[&Streak, &FirstLevel, &BestDay, &TotalPix];
+
+/// 連続でPIXを得た日数
+struct Streak;
+
+impl Streak {
+    const TIERS: [u32; 7] = [3, 7, 14, 30, 60, 100, 365];
+}
+
+impl Rule for Streak {
+    fn kind(&self) -> &'static str {
+        "streak"
+    }
+
+    fn evaluate(&self, history: &History) -> Vec<Award> {
+        let mut awards = Vec::new();
+        let mut length = 0;
+        let mut prev: Option<NaiveDate> = None;
+        for (date, amount) in history.daily {
+            if *amount == 0 {
+                length = 0;
+                continue;
+            }
+            length = match prev {
+                Some(p) if p.succ_opt() == Some(*date) && length > 0 => length + 1,
+                _ => 1,
+            };
+            prev = Some(*date);
+            // 同じ段階は最初に到達した日のみ
+            if Self::TIERS.contains(&length) && awards.iter().all(|a: &Award| a.value != length) {
+                awards.push(Award {
+                    kind: self.kind(),
+                    tier: length.to_string(),
+                    value: length,
+                    earned_on: *date,
+                });
+            }
+        }
+        awards
+    }
+}
+
+/// 各PgnLevelに初めて到達した日
+struct FirstLevel;
+
+impl Rule for FirstLevel {
+    fn kind(&self) -> &'static str {
+        "first_level"
+    }
+
+    fn evaluate(&self, history: &History) -> Vec<Award> {
+        let mut awards = Vec::new();
+        let mut highest = PgnLevel::Iron;
+        for point in history.levels {
+            // 複数の段階を一度に飛び越えた場合は、間のレベルも同じ日に到達したとみなす
+            while highest < point.level {
+                highest += 1;
+                awards.push(Award {
+                    kind: self.kind(),
+                    tier: highest.to_string(),
+                    value: point.window_pix,
+                    earned_on: point.date,
+                });
+            }
+        }
+        awards
+    }
+}
+
+/// 1日に得たPIXの自己ベスト
+struct BestDay;
+
+impl Rule for BestDay {
+    fn kind(&self) -> &'static str {
+        "best_day"
+    }
+
+    fn replaceable(&self) -> bool {
+        true
+    }
+
+    fn evaluate(&self, history: &History) -> Vec<Award> {
+        // 同じ記録の場合は最初の日
+        history
+            .daily
+            .iter()
+            .filter(|(_, amount)| **amount > history.best_before)
+            .rev()
+            .max_by_key(|(_, amount)| **amount)
+            .map(|(date, amount)| Award {
+                kind: self.kind(),
+                tier: String::new(),
+                value: *amount,
+                earned_on: *date,
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+/// 累計のPIX
+struct TotalPix;
+
+impl TotalPix {
+    const TIERS: [u32; 6] = [1_000, 10_000, 50_000, 100_000, 500_000, 1_000_000];
+}
+
+impl Rule for TotalPix {
+    fn kind(&self) -> &'static str {
+        "total_pix"
+    }
+
+    fn evaluate(&self, history: &History) -> Vec<Award> {
+        let mut awards = Vec::new();
+        let mut total = history.total_before;
+        // 既に到達している段階は飛ばす
+        let mut tiers = Self::TIERS
+            .iter()
+            .skip_while(|tier| **tier <= history.total_before)
+            .peekable();
+        for (date, amount) in history.daily {
+            total = total.saturating_add(*amount);
+            while let Some(tier) = tiers.next_if(|tier| **tier <= total) {
+                awards.push(Award {
+                    kind: self.kind(),
+                    tier: tier.to_string(),
+                    value: total,
+                    earned_on: *date,
+                });
+            }
+        }
+        awards
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    /// 初日から続く日毎のPIX
+    fn daily(from: &str, amounts: &[u32]) -> BTreeMap<NaiveDate, u32> {
+        date(from)
+            .iter_days()
+            .zip(amounts.iter().copied())
+            .collect()
+    }
+
+    fn evaluate(rule: &dyn Rule, history: &History) -> Vec<(String, u32, NaiveDate)> {
+        rule.evaluate(history)
+            .into_iter()
+            .map(|a| {
+                assert_eq!(a.kind, rule.kind());
+                (a.tier, a.value, a.earned_on)
+            })
+            .collect()
+    }
+
+    fn history<'a>(daily: &'a BTreeMap<NaiveDate, u32>, levels: &'a [LevelPoint]) -> History<'a> {
+        History {
+            daily,
+            levels,
+            total_before: 0,
+            best_before: 0,
+        }
+    }
+
+    #[test]
+    fn streak_counts_consecutive_days_with_pix() {
+        let mut daily = daily("2024-03-01", &[1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1]);
+        // 記録の無い日も途切れたとみなす
+        daily.insert(date("2024-03-20"), 1);
+        daily.insert(date("2024-03-21"), 1);
+        daily.insert(date("2024-03-23"), 1);
+        let awards = evaluate(&Streak, &history(&daily, &[]));
+        // 2回目の3日連続は記録しない
+        assert_eq!(
+            awards,
+            [
+                ("3".to_string(), 3, date("2024-03-03")),
+                ("7".to_string(), 7, date("2024-03-11")),
+            ]
+        );
+    }
+
+    #[test]
+    fn first_level_fills_skipped_levels() {
+        let point = |d: &str, window_pix, level| LevelPoint {
+            date: date(d),
+            window_pix,
+            level,
+        };
+        let levels = [
+            point("2024-03-01", 0, PgnLevel::Iron),
+            point("2024-03-02", 1200, PgnLevel::Silver),
+            point("2024-03-03", 600, PgnLevel::Bronze),
+            point("2024-03-04", 1500, PgnLevel::Silver),
+            point("2024-03-05", 3000, PgnLevel::Gold),
+        ];
+        let awards = evaluate(&FirstLevel, &history(&BTreeMap::new(), &levels));
+        assert_eq!(
+            awards,
+            [
+                ("Bronze".to_string(), 1200, date("2024-03-02")),
+                ("Silver".to_string(), 1200, date("2024-03-02")),
+                ("Gold".to_string(), 3000, date("2024-03-05")),
+            ]
+        );
+    }
+
+    #[test]
+    fn best_day_keeps_first_of_ties() {
+        let daily = daily("2024-03-01", &[5, 9, 3, 9]);
+        assert_eq!(
+            evaluate(&BestDay, &history(&daily, &[])),
+            [(String::new(), 9, date("2024-03-02"))]
+        );
+        assert!(evaluate(&BestDay, &history(&self::daily("2024-03-01", &[0, 0]), &[])).is_empty());
+    }
+
+    #[test]
+    fn best_day_needs_to_beat_earlier_history() {
+        let daily = daily("2024-03-01", &[5, 9, 12]);
+        let history = |best_before| History {
+            best_before,
+            ..history(&daily, &[])
+        };
+        assert!(evaluate(&BestDay, &history(12)).is_empty());
+        assert_eq!(
+            evaluate(&BestDay, &history(10)),
+            [(String::new(), 12, date("2024-03-03"))]
+        );
+    }
+
+    #[test]
+    fn total_pix_awards_every_crossed_tier() {
+        let daily = daily("2024-03-01", &[400, 700, 9_000, 50]);
+        assert_eq!(
+            evaluate(&TotalPix, &history(&daily, &[])),
+            [
+                ("1000".to_string(), 1_100, date("2024-03-02")),
+                ("10000".to_string(), 10_100, date("2024-03-03")),
+            ]
+        );
+    }
+
+    #[test]
+    fn total_pix_continues_from_earlier_total() {
+        let daily = daily("2024-03-01", &[400, 700]);
+        let history = History {
+            total_before: 49_000,
+            ..history(&daily, &[])
+        };
+        // 1000と10000は到達済み
+        assert_eq!(
+            evaluate(&TotalPix, &history),
+            [("50000".to_string(), 50_100, date("2024-03-02"))]
+        );
+    }
+}
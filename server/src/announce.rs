@@ -0,0 +1,335 @@
+//! レベルアップしたユーザをPgrit(Mastodon)のstatuses APIで投稿する。
+//! 投稿は本人が有効にした場合のみ行い、botアカウントか本人のトークンのどちらで投稿するかを選べる
+
+use reqwest::{header, Url};
+use sea_orm::DatabaseConnection;
+
+use crate::usecase::{self, LevelUpdate};
+
+/// 既定の投稿文; `{pgrit_id}`, `{from}`, `{to}`, `{pix}`, `{url}`を置き換える
+pub const DEFAULT_TEMPLATE: &str =
+    "{pgrit_id} さんのPgnLevelが {from} から {to} に上がりました！ (月間 {pix} PIX)\n{url}";
+
+/// レベルアップの投稿の設定
+pub struct Announcer {
+    /// statuses APIのURL
+    statuses_url: Url,
+    /// botアカウントのアクセストークン
+    bot_token: Option<String>,
+    /// 投稿文のテンプレート
+    template: String,
+    /// プロフィールのURLに用いるオリジン
+    origin: String,
+    /// 投稿せずに内容を出力するだけにするか
+    dry_run: bool,
+}
+
+impl Announcer {
+    pub fn new(
+        statuses_url: Url,
+        bot_token: Option<String>,
+        template: Option<String>,
+        origin: &str,
+        dry_run: bool,
+    ) -> Self {
+        Announcer {
+            statuses_url,
+            bot_token,
+            template: template.unwrap_or_else(|| DEFAULT_TEMPLATE.to_string()),
+            origin: origin.to_string(),
+            dry_run,
+        }
+    }
+
+    /// テンプレートから投稿文を生成する
+    fn status(&self, update: &LevelUpdate) -> String {
+        self.template
+            .replace("{pgrit_id}", &update.user.pgrit_id)
+            .replace("{from}", update.from.as_ref())
+            .replace("{to}", update.to.as_ref())
+            .replace("{pix}", &update.last_month.to_string())
+            .replace(
+                "{url}",
+                &format!("{}/profile/{}/", self.origin, update.user.pgrit_id),
+            )
+    }
+
+    /// レベルが上がったユーザのうち、投稿を有効にしているユーザについて投稿する。
+    /// 1件の失敗で他のユーザの投稿が止まらないよう、失敗はログに残して続行する
+    pub async fn announce(
+        &self,
+        db: &DatabaseConnection,
+        updates: &[LevelUpdate],
+    ) -> Result<(), entity::error::Error> {
+        let level_ups = updates.iter().filter(|u| u.to > u.from).collect::<Vec<_>>();
+        if level_ups.is_empty() {
+            return Ok(());
+        }
+        let mut targets =
+            usecase::announcement_targets(db, level_ups.iter().map(|u| u.user.id.clone())).await?;
+
+        let client = reqwest::Client::new();
+        for update in level_ups {
+            let Some((setting, token)) = targets.remove(&update.user.id) else {
+                continue;
+            };
+            let token = if setting.use_own_token {
+                // 投稿を許可していないトークンでは403になるので使わない
+                token.filter(|t| t.can_post()).map(|t| t.access_token)
+            } else {
+                self.bot_token.clone()
+            };
+            let Some(token) = token else {
+                eprintln!(
+                    "No token to announce the level-up of {}",
+                    update.user.pgrit_id
+                );
+                continue;
+            };
+            let status = self.status(update);
+            if self.dry_run {
+                println!(
+                    "[dry-run] {} ({}): {}",
+                    update.user.pgrit_id,
+                    if setting.use_own_token { "own" } else { "bot" },
+                    status
+                );
+                continue;
+            }
+            let result = client
+                .post(self.statuses_url.clone())
+                .bearer_auth(token)
+                // 再試行しても同じレベルアップが二重に投稿されないようにする
+                .header(
+                    "Idempotency-Key",
+                    format!("pgnpg-{}-{}", update.user.id, update.to),
+                )
+                .header(header::ACCEPT, "application/json")
+                .form(&[("status", status.as_str())])
+                .send()
+                .await
+                .and_then(|res| res.error_for_status());
+            if let Err(e) = result {
+                eprintln!(
+                    "Failed to announce the level-up of {}: {}",
+                    update.user.pgrit_id, e
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{extract::State, http::HeaderMap, routing::post, Form, Router};
+    use entity::{announcement, mstdn_token, pgn_level::PgnLevel, user};
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{Database, EntityTrait, Set};
+
+    use super::*;
+
+    /// statuses APIが受け取った投稿; (Authorization, Idempotency-Key, status)
+    type Posted = Arc<Mutex<Vec<(String, String, String)>>>;
+
+    /// 受け取った投稿を記録するだけのstatuses APIを立てる
+    async fn stand_in() -> (Url, Posted) {
+        let posted = Posted::default();
+        let app = Router::new()
+            .route(
+                "/api/v1/statuses",
+                post(
+                    |State(posted): State<Posted>,
+                     headers: HeaderMap,
+                     Form(form): Form<HashMap<String, String>>| async move {
+                        let header = |name: &str| {
+                            headers
+                                .get(name)
+                                .and_then(|v| v.to_str().ok())
+                                .unwrap_or_default()
+                                .to_string()
+                        };
+                        posted.lock().unwrap().push((
+                            header("authorization"),
+                            header("idempotency-key"),
+                            form.get("status").cloned().unwrap_or_default(),
+                        ));
+                        "{}"
+                    },
+                ),
+            )
+            .with_state(posted.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/v1/statuses", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (Url::parse(&url).unwrap(), posted)
+    }
+
+    fn user(id: &str) -> user::Model {
+        user::Model {
+            id: id.to_string(),
+            pgrit_id: format!("pgrit_{}", id),
+        }
+    }
+
+    /// 投稿の設定; `scope`がSomeの場合はその許可を持つトークンも登録する
+    async fn setup(settings: &[(&str, bool, bool, Option<&str>)]) -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        for (id, enabled, use_own_token, scope) in settings {
+            user::Entity::insert(user::ActiveModel::from(user(id)))
+                .exec(&db)
+                .await
+                .unwrap();
+            announcement::Entity::insert(announcement::ActiveModel {
+                user_id: Set(id.to_string()),
+                enabled: Set(*enabled),
+                use_own_token: Set(*use_own_token),
+            })
+            .exec(&db)
+            .await
+            .unwrap();
+            if let Some(scope) = scope {
+                mstdn_token::Entity::insert(mstdn_token::ActiveModel {
+                    user_id: Set(id.to_string()),
+                    authorization_code: Set("code".to_string()),
+                    access_token: Set(format!("token_{}", id)),
+                    scope: Set(Some(scope.to_string())),
+                })
+                .exec(&db)
+                .await
+                .unwrap();
+            }
+        }
+        db
+    }
+
+    fn level_up(id: &str, from: PgnLevel, to: PgnLevel) -> LevelUpdate {
+        LevelUpdate {
+            user: user(id),
+            from,
+            to,
+            last_month: 1234,
+        }
+    }
+
+    #[tokio::test]
+    async fn posts_level_ups_with_chosen_token() {
+        let db = setup(&[
+            ("bot", true, false, None),
+            ("own", true, true, Some("read:accounts write:statuses")),
+        ])
+        .await;
+        let (url, posted) = stand_in().await;
+        let announcer = Announcer::new(
+            url,
+            Some("bot_token".to_string()),
+            Some("{pgrit_id}: {from} -> {to} ({pix}) {url}".to_string()),
+            "https://pgnpg.example",
+            false,
+        );
+        announcer
+            .announce(
+                &db,
+                &[
+                    level_up("bot", PgnLevel::Iron, PgnLevel::Bronze),
+                    level_up("own", PgnLevel::Silver, PgnLevel::Gold),
+                ],
+            )
+            .await
+            .unwrap();
+
+        let mut posted = posted.lock().unwrap().clone();
+        posted.sort();
+        assert_eq!(
+            posted,
+            [
+                (
+                    "Bearer bot_token".to_string(),
+                    "pgnpg-bot-Bronze".to_string(),
+                    "pgrit_bot: Iron -> Bronze (1234) https://pgnpg.example/profile/pgrit_bot/"
+                        .to_string()
+                ),
+                (
+                    "Bearer token_own".to_string(),
+                    "pgnpg-own-Gold".to_string(),
+                    "pgrit_own: Silver -> Gold (1234) https://pgnpg.example/profile/pgrit_own/"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_users_without_setting_or_permission() {
+        let db = setup(&[
+            ("disabled", false, false, None),
+            ("login_only", true, true, Some("read:accounts")),
+            ("no_bot", true, false, None),
+            ("down", true, false, None),
+        ])
+        .await;
+        let (url, posted) = stand_in().await;
+        // botのトークンが無ければbotからは投稿できない
+        let announcer = Announcer::new(url.clone(), None, None, "https://pgnpg.example", false);
+        announcer
+            .announce(
+                &db,
+                &[
+                    level_up("disabled", PgnLevel::Iron, PgnLevel::Bronze),
+                    level_up("login_only", PgnLevel::Iron, PgnLevel::Bronze),
+                    level_up("no_bot", PgnLevel::Iron, PgnLevel::Bronze),
+                    level_up("unknown", PgnLevel::Iron, PgnLevel::Bronze),
+                ],
+            )
+            .await
+            .unwrap();
+        // 降格は投稿しない
+        let announcer = Announcer::new(url, Some("bot_token".to_string()), None, "", false);
+        announcer
+            .announce(&db, &[level_up("down", PgnLevel::Gold, PgnLevel::Silver)])
+            .await
+            .unwrap();
+
+        assert!(posted.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn dry_run_does_not_post() {
+        let db = setup(&[("bot", true, false, None)]).await;
+        let (url, posted) = stand_in().await;
+        let announcer = Announcer::new(
+            url,
+            Some("bot_token".to_string()),
+            None,
+            "https://pgnpg.example",
+            true,
+        );
+        announcer
+            .announce(&db, &[level_up("bot", PgnLevel::Iron, PgnLevel::Bronze)])
+            .await
+            .unwrap();
+
+        assert!(posted.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn default_template() {
+        let announcer = Announcer::new(
+            Url::parse("http://localhost/").unwrap(),
+            None,
+            None,
+            "https://pgnpg.example",
+            false,
+        );
+        assert_eq!(
+            announcer.status(&level_up("a", PgnLevel::Bronze, PgnLevel::Silver)),
+            "pgrit_a さんのPgnLevelが Bronze から Silver に上がりました！ (月間 1234 PIX)\nhttps://pgnpg.example/profile/pgrit_a/"
+        );
+    }
+}
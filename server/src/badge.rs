@@ -0,0 +1,279 @@
+//! プロフィールやREADMEに埋め込むための、現在のPgnLevelを表すSVGバッジ
+
+use entity::{pgn_level::PgnLevel, user_profile::PgnInfo};
+use serde::Deserialize;
+
+/// バッジの見た目
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Style {
+    /// 角の丸い平坦なバッジ
+    #[default]
+    Flat,
+    /// 角の丸くない平坦なバッジ
+    FlatSquare,
+    /// 光沢のあるバッジ
+    Plastic,
+}
+
+/// バッジの表示オプション
+#[derive(Deserialize)]
+pub struct BadgeQuery {
+    #[serde(default)]
+    pub style: Style,
+    /// 左側に表示する文字列
+    pub label: Option<String>,
+    /// 最近1ヶ月のPIXを表示するか
+    pub pix: Option<bool>,
+    /// 次のレベルまでの進捗バーを表示するか
+    pub progress: Option<bool>,
+}
+
+const HEIGHT: u32 = 20;
+const PADDING: u32 = 6;
+const LABEL_COLOR: &str = "#555";
+/// グラマスの背景; クライアントの表示に合わせて虹色にする
+const RAINBOW: [&str; 7] = [
+    "#FF0000", "#FF7F00", "#FFFF00", "#00FF00", "#0000FF", "#4B0082", "#8B00FF",
+];
+
+/// PgnLevelの色; クライアントの表示と揃える。グラマスは虹色なのでNone
+pub(crate) fn color(level: PgnLevel) -> Option<&'static str> {
+    match level {
+        PgnLevel::Iron => Some("#ac9393"),
+        PgnLevel::Bronze => Some("#aa4400"),
+        PgnLevel::Silver => Some("#a7a7a7"),
+        PgnLevel::Gold => Some("#c8ab37"),
+        PgnLevel::Platinum => Some("#000000"),
+        PgnLevel::Diamond => Some("#00aad4"),
+        PgnLevel::Master => Some("#6A0DAD"),
+        PgnLevel::GrandMaster => None,
+    }
+}
+
+/// グラマス用の虹色のグラデーションを、指定したIDで定義する
+pub(crate) fn rainbow(id: &str) -> String {
+    let stops = RAINBOW
+        .iter()
+        .enumerate()
+        .map(|(i, color)| {
+            format!(
+                r#"<stop offset="{:.3}" stop-color="{}"/>"#,
+                i as f32 / (RAINBOW.len() - 1) as f32,
+                color
+            )
+        })
+        .collect::<String>();
+    format!(r#"<linearGradient id="{}">{}</linearGradient>"#, id, stops)
+}
+
+/// Verdana 11pxでの文字列の幅の概算
+fn text_width(text: &str) -> u32 {
+    let width: f32 = text
+        .chars()
+        .map(|c| match c {
+            'i' | 'j' | 'l' | '.' | ',' | ':' | ';' | '!' | '|' | '\'' => 3.5,
+            'f' | 't' | 'r' | 'I' | ' ' | '(' | ')' | '[' | ']' | '-' => 4.5,
+            'm' | 'w' | 'M' | 'W' => 10.0,
+            c if c.is_ascii_uppercase() || c.is_ascii_digit() => 7.5,
+            c if c.is_ascii() => 6.5,
+            // 全角文字
+            _ => 11.0,
+        })
+        .sum();
+    width.ceil() as u32
+}
+
+/// XMLのテキストや属性値として埋め込めるようにエスケープする
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// PGN情報からバッジを描画する
+pub fn render(pgn: &PgnInfo, query: &BadgeQuery) -> String {
+    let label = query.label.as_deref().unwrap_or("PGN");
+    let message = if query.pix.unwrap_or(true) {
+        format!("{} | {} PIX", pgn.level, pgn.last_month)
+    } else {
+        pgn.level.to_string()
+    };
+    let progress = query
+        .progress
+        .unwrap_or(true)
+        .then_some(pgn.progress)
+        .flatten();
+
+    let label_width = text_width(label) + PADDING * 2;
+    let message_width = text_width(&message) + PADDING * 2;
+    let width = label_width + message_width;
+    let radius = if query.style == Style::FlatSquare {
+        0
+    } else {
+        3
+    };
+
+    let mut defs = String::new();
+    let message_fill = match color(pgn.level) {
+        Some(color) => color.to_string(),
+        None => {
+            defs.push_str(&rainbow("r"));
+            "url(#r)".to_string()
+        }
+    };
+    if query.style != Style::FlatSquare {
+        let top = if query.style == Style::Plastic {
+            ".2"
+        } else {
+            ".1"
+        };
+        defs.push_str(&format!(
+            r##"<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity="{}"/><stop offset="1" stop-opacity=".1"/></linearGradient>"##,
+            top
+        ));
+    }
+    let gloss = if query.style == Style::FlatSquare {
+        String::new()
+    } else {
+        format!(
+            r#"<rect width="{}" height="{}" fill="url(#s)"/>"#,
+            width, HEIGHT
+        )
+    };
+    let progress_bar = progress
+        .map(|progress| {
+            format!(
+                r##"<rect x="{}" y="{}" width="{:.1}" height="2" fill="#fff" fill-opacity=".7"/>"##,
+                label_width,
+                HEIGHT - 2,
+                message_width as f32 * progress.clamp(0.0, 1.0)
+            )
+        })
+        .unwrap_or_default();
+
+    let label = escape(label);
+    let message = escape(&message);
+    format!(
+        concat!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" role="img" aria-label="{label}: {message}">"##,
+            r##"<title>{label}: {message}</title>"##,
+            r##"<defs>{defs}<clipPath id="c"><rect width="{width}" height="{height}" rx="{radius}" fill="#fff"/></clipPath></defs>"##,
+            r##"<g clip-path="url(#c)">"##,
+            r##"<rect width="{label_width}" height="{height}" fill="{label_color}"/>"##,
+            r##"<rect x="{label_width}" width="{message_width}" height="{height}" fill="{message_fill}"/>"##,
+            r##"{progress_bar}{gloss}</g>"##,
+            r##"<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">"##,
+            r##"<text x="{label_x}" y="15" fill="#010101" fill-opacity=".3">{label}</text>"##,
+            r##"<text x="{label_x}" y="14">{label}</text>"##,
+            r##"<text x="{message_x}" y="15" fill="#010101" fill-opacity=".3">{message}</text>"##,
+            r##"<text x="{message_x}" y="14">{message}</text>"##,
+            r##"</g></svg>"##,
+        ),
+        width = width,
+        height = HEIGHT,
+        radius = radius,
+        defs = defs,
+        label = label,
+        message = message,
+        label_width = label_width,
+        message_width = message_width,
+        label_color = LABEL_COLOR,
+        message_fill = message_fill,
+        progress_bar = progress_bar,
+        gloss = gloss,
+        label_x = label_width as f32 / 2.0,
+        message_x = label_width as f32 + message_width as f32 / 2.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use entity::user_profile::Forecast;
+
+    use super::*;
+
+    fn pgn(level: PgnLevel, last_month: u32, progress: Option<f32>) -> PgnInfo {
+        PgnInfo {
+            updated_at: Utc::now(),
+            daily: Default::default(),
+            level,
+            last_month,
+            on_level: 0,
+            level_length: None,
+            progress,
+            target: None,
+            behind_next: None,
+            sources: None,
+            season: None,
+            forecast: Forecast {
+                expiring: 0,
+                days: Vec::new(),
+                required_daily: 0,
+            },
+        }
+    }
+
+    fn query(label: Option<&str>) -> BadgeQuery {
+        BadgeQuery {
+            style: Style::Flat,
+            label: label.map(str::to_string),
+            pix: None,
+            progress: None,
+        }
+    }
+
+    #[test]
+    fn escape_xml_special_characters() {
+        assert_eq!(
+            escape(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;"
+        );
+        assert_eq!(escape("ぺんぎん"), "ぺんぎん");
+    }
+
+    #[test]
+    fn text_width_counts_non_ascii_as_full_width() {
+        assert_eq!(text_width("PGN"), 23);
+        assert_eq!(text_width("日本語"), 33);
+        assert_eq!(text_width("PGN日本"), 45);
+    }
+
+    #[test]
+    fn render_known_level() {
+        let svg = render(&pgn(PgnLevel::Bronze, 600, Some(0.2)), &query(None));
+        // ラベル: 23 + 12, メッセージ: 97 + 12
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="144" height="20""#)
+        );
+        assert!(svg.contains("<title>PGN: Bronze | 600 PIX</title>"));
+        assert!(svg.contains(r##"<rect x="35" width="109" height="20" fill="#aa4400"/>"##));
+        assert!(svg.contains(r##"<rect x="35" y="18" width="21.8" height="2""##));
+    }
+
+    #[test]
+    fn render_grand_master_with_rainbow() {
+        let svg = render(&pgn(PgnLevel::GrandMaster, 40000, None), &query(None));
+        assert!(svg.contains(r#"<linearGradient id="r">"#));
+        assert!(svg.contains(r#"fill="url(#r)""#));
+        assert!(!svg.contains(r#"height="2""#));
+    }
+
+    #[test]
+    fn render_escapes_label() {
+        let svg = render(&pgn(PgnLevel::Iron, 0, None), &query(Some("<b>&")));
+        assert!(!svg.contains("<b>"));
+        assert!(svg.contains("<title>&lt;b&gt;&amp;: Iron | 0 PIX</title>"));
+        assert!(svg.contains(r#"aria-label="&lt;b&gt;&amp;: Iron | 0 PIX""#));
+    }
+}
@@ -0,0 +1,305 @@
+//! 分析用にPIXや学生情報をCSV・NDJSONで書き出す。
+//! 全件をメモリに載せないよう、データベースから読み出した行を順にレスポンスへ流す
+
+use axum::body::Body;
+use chrono::NaiveDate;
+use entity::{degree::Degree, level::Level, pix, sex::Sex, student, user};
+use futures::{channel::mpsc, SinkExt, Stream, StreamExt};
+use sea_orm::{
+    prelude::Date, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, FromQueryResult,
+    QueryFilter, QueryOrder, QuerySelect, RelationTrait,
+};
+use serde::{Deserialize, Serialize};
+
+/// レスポンスに流す前にバッファしておく行数
+const BUFFER_ROWS: usize = 64;
+
+/// 書き出し形式
+#[derive(Clone, Copy)]
+pub enum Format {
+    Csv,
+    Ndjson,
+}
+
+impl Format {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Ndjson => "application/x-ndjson; charset=utf-8",
+        }
+    }
+
+    /// 行より前に書き出すもの; CSVのヘッダ。行がない場合も書き出す
+    fn header<T: Row>(&self) -> Result<Vec<u8>, anyhow::Error> {
+        match self {
+            Format::Csv => {
+                let mut writer = csv::Writer::from_writer(Vec::new());
+                writer.write_record(T::COLUMNS)?;
+                Ok(writer.into_inner()?)
+            }
+            Format::Ndjson => Ok(Vec::new()),
+        }
+    }
+
+    /// 1行分をエンコードする
+    fn encode(&self, row: &impl Serialize) -> Result<Vec<u8>, anyhow::Error> {
+        match self {
+            Format::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(Vec::new());
+                writer.serialize(row)?;
+                Ok(writer.into_inner()?)
+            }
+            Format::Ndjson => {
+                let mut line = serde_json::to_vec(row)?;
+                line.push(b'\n');
+                Ok(line)
+            }
+        }
+    }
+}
+
+/// 書き出す行の絞り込み条件; 期間はCLIの`export`と同じく[start, end)の半開区間
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    /// 期間の開始日(この日を含む)
+    pub start: Option<NaiveDate>,
+    /// 期間の終了日(この日を含まない)
+    pub end: Option<NaiveDate>,
+    /// Pgrit ID
+    pub user: Option<String>,
+}
+
+/// 書き出す行
+trait Row: Serialize {
+    /// CSVのヘッダに用いる列名; フィールドの順に並べる
+    const COLUMNS: &'static [&'static str];
+}
+
+/// 日毎のPIX
+#[derive(FromQueryResult, Serialize)]
+struct PixRow {
+    pgrit_id: String,
+    user_id: String,
+    date: Date,
+    amount: i32,
+}
+
+/// 学生情報
+#[derive(FromQueryResult, Serialize)]
+struct StudentRow {
+    pgrit_id: String,
+    user_id: String,
+    degree_step: Degree,
+    grade: i16,
+    course: String,
+    level: Level,
+    sex: Sex,
+    join_date: Date,
+    join_month: Option<String>,
+    office: String,
+    email: String,
+    email_of_4nonome: String,
+    university: String,
+    major: String,
+    leave_date: Option<Date>,
+    active: bool,
+    slack_id: String,
+    discord_id: Option<String>,
+}
+
+impl Row for PixRow {
+    const COLUMNS: &'static [&'static str] = &["pgrit_id", "user_id", "date", "amount"];
+}
+
+impl Row for StudentRow {
+    const COLUMNS: &'static [&'static str] = &[
+        "pgrit_id",
+        "user_id",
+        "degree_step",
+        "grade",
+        "course",
+        "level",
+        "sex",
+        "join_date",
+        "join_month",
+        "office",
+        "email",
+        "email_of_4nonome",
+        "university",
+        "major",
+        "leave_date",
+        "active",
+        "slack_id",
+        "discord_id",
+    ];
+}
+
+/// 日毎のPIXを書き出す。期間はPIXの日付に適用する
+pub fn pix(db: DatabaseConnection, format: Format, query: ExportQuery) -> Body {
+    let mut select = pix::Entity::find()
+        .select_only()
+        .column(user::Column::PgritId)
+        .columns([pix::Column::UserId, pix::Column::Date, pix::Column::Amount])
+        .join(sea_orm::JoinType::InnerJoin, pix::Relation::User.def())
+        .order_by_asc(pix::Column::UserId)
+        .order_by_asc(pix::Column::Date);
+    if let Some(start) = query.start {
+        select = select.filter(pix::Column::Date.gte(start));
+    }
+    if let Some(end) = query.end {
+        select = select.filter(pix::Column::Date.lt(end));
+    }
+    if let Some(pgrit_id) = query.user {
+        select = select.filter(user::Column::PgritId.eq(pgrit_id));
+    }
+
+    let (tx, rx) = mpsc::channel(BUFFER_ROWS);
+    tokio::spawn(async move {
+        let rows = select.into_model::<PixRow>().stream(&db).await;
+        forward(rows, format, tx).await;
+    });
+    Body::from_stream(rx)
+}
+
+/// 学生情報を書き出す。期間は加入日に適用する
+pub fn students(db: DatabaseConnection, format: Format, query: ExportQuery) -> Body {
+    let mut select = student::Entity::find()
+        .column(user::Column::PgritId)
+        .join(sea_orm::JoinType::InnerJoin, student::Relation::User.def())
+        .order_by_asc(student::Column::UserId);
+    if let Some(start) = query.start {
+        select = select.filter(student::Column::JoinDate.gte(start));
+    }
+    if let Some(end) = query.end {
+        select = select.filter(student::Column::JoinDate.lt(end));
+    }
+    if let Some(pgrit_id) = query.user {
+        select = select.filter(user::Column::PgritId.eq(pgrit_id));
+    }
+
+    let (tx, rx) = mpsc::channel(BUFFER_ROWS);
+    tokio::spawn(async move {
+        let rows = select.into_model::<StudentRow>().stream(&db).await;
+        forward(rows, format, tx).await;
+    });
+    Body::from_stream(rx)
+}
+
+/// データベースから読み出した行をエンコードしてレスポンスへ送る。
+/// 途中で失敗した場合はエラーを送り、不完全なデータが完全なものとして扱われないようにする
+async fn forward<T: Row>(
+    rows: Result<impl Stream<Item = Result<T, DbErr>>, DbErr>,
+    format: Format,
+    mut tx: mpsc::Sender<Result<Vec<u8>, anyhow::Error>>,
+) {
+    let mut rows = match rows {
+        Ok(rows) => std::pin::pin!(rows),
+        Err(e) => {
+            eprintln!("{:?}", e);
+            let _ = tx.send(Err(e.into())).await;
+            return;
+        }
+    };
+    let header = format.header::<T>();
+    if let Err(e) = &header {
+        eprintln!("{:?}", e);
+    }
+    let failed = header.is_err();
+    if tx.send(header).await.is_err() || failed {
+        return;
+    }
+    while let Some(row) = rows.next().await {
+        let chunk = row
+            .map_err(anyhow::Error::from)
+            .and_then(|row| format.encode(&row));
+        if let Err(e) = &chunk {
+            eprintln!("{:?}", e);
+        }
+        let failed = chunk.is_err();
+        // クライアントが切断した場合は読み出しを中止する
+        if tx.send(chunk).await.is_err() || failed {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+
+    /// 行を書き出し、レスポンスの本文を返す
+    async fn body<T: Row>(rows: Vec<T>, format: Format) -> String {
+        let (tx, rx) = mpsc::channel(BUFFER_ROWS);
+        forward(Ok(stream::iter(rows.into_iter().map(Ok))), format, tx).await;
+        let chunks = rx.map(Result::unwrap).collect::<Vec<_>>().await;
+        String::from_utf8(chunks.concat()).unwrap()
+    }
+
+    fn pix_row(date: &str, amount: i32) -> PixRow {
+        PixRow {
+            pgrit_id: "alice".to_string(),
+            user_id: "0xa".to_string(),
+            date: date.parse().unwrap(),
+            amount,
+        }
+    }
+
+    #[tokio::test]
+    async fn csv_has_header_even_without_rows() {
+        assert_eq!(
+            body(Vec::<PixRow>::new(), Format::Csv).await,
+            "pgrit_id,user_id,date,amount\n"
+        );
+        assert_eq!(body(Vec::<PixRow>::new(), Format::Ndjson).await, "");
+    }
+
+    #[tokio::test]
+    async fn csv_has_header_once() {
+        let rows = vec![pix_row("2024-05-01", 10), pix_row("2024-05-02", 20)];
+        assert_eq!(
+            body(rows, Format::Csv).await,
+            "pgrit_id,user_id,date,amount\nalice,0xa,2024-05-01,10\nalice,0xa,2024-05-02,20\n"
+        );
+    }
+
+    /// 列名がフィールドの順と一致する
+    #[test]
+    fn columns_match_fields() {
+        fn serialized_header(row: &impl Serialize) -> String {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.serialize(row).unwrap();
+            let csv = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+            csv.lines().next().unwrap().to_string()
+        }
+
+        assert_eq!(
+            serialized_header(&pix_row("2024-05-01", 10)),
+            PixRow::COLUMNS.join(",")
+        );
+        let student = StudentRow {
+            pgrit_id: "alice".to_string(),
+            user_id: "0xa".to_string(),
+            degree_step: Degree::Bachelor,
+            grade: 1,
+            course: "course".to_string(),
+            level: Level::Newbie,
+            sex: Sex::Female,
+            join_date: "2024-04-01".parse().unwrap(),
+            join_month: None,
+            office: "office".to_string(),
+            email: "alice@example.com".to_string(),
+            email_of_4nonome: "alice@example.com".to_string(),
+            university: "university".to_string(),
+            major: "major".to_string(),
+            leave_date: None,
+            active: true,
+            slack_id: "U1".to_string(),
+            discord_id: None,
+        };
+        assert_eq!(serialized_header(&student), StudentRow::COLUMNS.join(","));
+    }
+}
@@ -1,33 +1,48 @@
-mod usecase;
+mod achievement;
+mod announce;
+mod badge;
+mod export;
+mod listener;
+pub mod notify;
+mod og;
+mod scheduler;
+mod session_store;
+pub mod usecase;
 
-use entity::user;
-use std::{collections::HashSet, path::PathBuf, sync::Arc};
+use entity::{
+    announcement, error::Error, goal, group_stats::GroupBy, public_profile,
+    refresh_status::RefreshStatus, user,
+};
+use std::{collections::HashSet, net::SocketAddr, path::PathBuf, sync::Arc};
 use time::Duration;
 
 use axum::{
+    body::Body,
     extract::{Path, Query, Request},
     http::StatusCode,
     middleware::{self, Next},
     response::{IntoResponse, Redirect, Response},
-    routing::{any, get},
-    Router,
+    routing::{any, get, put},
+    Json, Router,
 };
 use chrono::Local;
+use itertools::Itertools;
 use reqwest::{header, Url};
 use sea_orm::DatabaseConnection;
 use serde::{Deserialize, Serialize};
-use tower_http::{
-    compression::CompressionLayer,
-    services::{ServeDir, ServeFile},
-};
+use tower_http::{compression::CompressionLayer, services::ServeDir};
 use tower_sessions::{Expiry, Session, SessionManagerLayer};
-use tower_sessions_sqlx_store::SqliteStore;
-use usecase::profile;
 
-use crate::usecase::{get_last_updated_at, signup};
+use crate::usecase::{get_last_updated_at, signup, RefreshError};
 
 const DAYS_COUNT: i64 = 30;
 
+/// 投稿を許可する認可の要求であることを表すOAuthのstate
+const POSTING_STATE: &str = "posting";
+
+/// 一度に比較できるユーザ数
+const MAX_COMPARE_USERS: usize = 10;
+
 static RUNNING_REFRESH: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
 
 /// charset=utf-8 に対応したJSONレスポンスを生成する
@@ -39,23 +54,102 @@ fn json(content: impl Serialize) -> impl IntoResponse {
     )
 }
 
-struct Deferer();
+/// 更新処理の実行中フラグを保持し、Drop時に必ず解放する
+struct RefreshLock(());
 
-impl Drop for Deferer {
+impl RefreshLock {
+    /// 実行中でなければフラグを立てて取得する
+    fn acquire() -> Option<Self> {
+        RUNNING_REFRESH
+            .compare_exchange(
+                false,
+                true,
+                std::sync::atomic::Ordering::AcqRel,
+                std::sync::atomic::Ordering::Acquire,
+            )
+            .ok()
+            .map(|_| RefreshLock(()))
+    }
+}
+
+impl Drop for RefreshLock {
     fn drop(&mut self) {
-        RUNNING_REFRESH.store(false, std::sync::atomic::Ordering::Relaxed);
+        RUNNING_REFRESH.store(false, std::sync::atomic::Ordering::Release);
     }
 }
 
+/// リフレッシュ後にレベルの変化などを伝える先
+#[derive(Default)]
+struct Notifiers {
+    /// Pgritへのレベルアップの投稿
+    announcer: Option<announce::Announcer>,
+    /// SlackやDiscordへの通知
+    notifier: Option<notify::Notifier>,
+}
+
 /// Spawnされる更新処理タスク
-async fn refresh(db: &DatabaseConnection, fetch_url: &str) {
-    if RUNNING_REFRESH.load(std::sync::atomic::Ordering::Relaxed) {
-        return;
+async fn refresh(
+    db: &DatabaseConnection,
+    fetch_url: &str,
+    notifiers: &Notifiers,
+) -> Result<(), RefreshError> {
+    let Some(_lock) = RefreshLock::acquire() else {
+        return Ok(());
+    };
+
+    let previous = usecase::last_refresh_status(db)
+        .await
+        .map_err(RefreshError::Job)?;
+    let job = usecase::start_refresh_job(db, chrono::Utc::now())
+        .await
+        .map_err(RefreshError::Job)?;
+
+    let mut report = usecase::RefreshReport::default();
+    let result = refresh_records(db, fetch_url, &mut report).await;
+    let (status, error) = match &result {
+        Ok(status) => (*status, None),
+        Err(e) => (RefreshStatus::Failed, Some(e.to_string())),
+    };
+
+    if status == RefreshStatus::Skipped && previous == Some(RefreshStatus::Skipped) {
+        // スケジューラの間隔が短いと中止が続くので、最初の1回だけを記録する
+        usecase::discard_refresh_job(db, job)
+            .await
+            .map_err(RefreshError::Job)?;
     } else {
-        RUNNING_REFRESH.store(true, std::sync::atomic::Ordering::Relaxed);
+        usecase::finish_refresh_job(db, job, chrono::Utc::now(), status, report, error)
+            .await
+            .map_err(RefreshError::Job)?;
     }
-    let _  = Deferer();
 
+    if status == RefreshStatus::Succeeded {
+        // 通知の失敗はリフレッシュ自体の失敗としない
+        match usecase::update_levels(db, chrono::Utc::now()).await {
+            Ok(updates) => {
+                if let Some(announcer) = &notifiers.announcer {
+                    if let Err(e) = announcer.announce(db, &updates).await {
+                        eprintln!("Failed to announce level-ups: {}", e);
+                    }
+                }
+                if let Some(notifier) = &notifiers.notifier {
+                    if let Err(e) = notifier.notify(db, chrono::Utc::now(), &updates).await {
+                        eprintln!("Failed to send notifications: {}", e);
+                    }
+                }
+            }
+            Err(e) => eprintln!("Failed to update levels: {}", e),
+        }
+    }
+
+    result.map(|_| ())
+}
+
+/// データを取得してデータベースに書き込む
+async fn refresh_records(
+    db: &DatabaseConnection,
+    fetch_url: &str,
+    report: &mut usecase::RefreshReport,
+) -> Result<RefreshStatus, RefreshError> {
     let now = chrono::Utc::now();
 
     let mut active_users_pre: Option<_> = None;
@@ -63,14 +157,19 @@ async fn refresh(db: &DatabaseConnection, fetch_url: &str) {
     // 最初に最低限必要な日付を取得
     let end = now.with_timezone(&Local).date_naive();
     // 最低一日は取得
-    let start = if let Some(last_datetime) = get_last_updated_at(db).await.unwrap() {
+    let start = if let Some(last_datetime) = get_last_updated_at(db)
+        .await
+        .map_err(RefreshError::LastUpdatedAt)?
+    {
         // 30分以上の間隔がない場合は中止
         if now - last_datetime < chrono::Duration::minutes(30) {
-            return;
+            return Ok(RefreshStatus::Skipped);
         }
 
         // 更新されているユーザの数を数える
-        active_users_pre = usecase::active_users(db).await.unwrap();
+        active_users_pre = usecase::active_users(db)
+            .await
+            .map_err(RefreshError::ActiveUsers)?;
 
         last_datetime
             .with_timezone(&Local)
@@ -81,9 +180,13 @@ async fn refresh(db: &DatabaseConnection, fetch_url: &str) {
     } else {
         end - chrono::Duration::days(DAYS_COUNT - 1)
     };
+    report.start_date = Some(start);
+    report.end_date = Some(end);
 
     // データを取得
-    let mut records = usecase::fetch(fetch_url, start, end).await.unwrap();
+    let (mut records, mut warnings) = usecase::fetch_with_retry(fetch_url, start, end)
+        .await
+        .map_err(RefreshError::Fetch)?;
 
     // 十分性の確認(新しいユーザがいた場合は再度取得)
     if let Some(active_users_pre) = active_users_pre {
@@ -95,17 +198,82 @@ async fn refresh(db: &DatabaseConnection, fetch_url: &str) {
             .collect::<HashSet<_>>()
             != active_users_post
         {
-            records = usecase::fetch(fetch_url, end - chrono::Duration::days(DAYS_COUNT - 1), end)
+            let start = end - chrono::Duration::days(DAYS_COUNT - 1);
+            report.start_date = Some(start);
+            report.refetched = true;
+            (records, warnings) = usecase::fetch_with_retry(fetch_url, start, end)
                 .await
-                .unwrap();
+                .map_err(RefreshError::Fetch)?;
         }
     }
+    report.records_fetched = Some(records.len() as u32);
+    if !warnings.is_empty() {
+        eprintln!("{} records had invalid fields", warnings.len());
+    }
+    report.warnings = warnings;
 
-    usecase::insert(db, chrono::Utc::now(), records)
+    let rows_upserted = usecase::insert(db, chrono::Utc::now(), records, true)
         .await
-        .unwrap();
+        .map_err(RefreshError::Insert)?;
+    report.rows_upserted = Some(rows_upserted as u32);
+
+    Ok(RefreshStatus::Succeeded)
+}
+
+#[derive(Deserialize)]
+struct GroupStatsQuery {
+    /// office, university, course, major, level, degree_step
+    by: GroupBy,
+}
 
-    RUNNING_REFRESH.store(false, std::sync::atomic::Ordering::Relaxed);
+/// PgnInfoを計算する期間の指定; 期間は常に30日間
+#[derive(Deserialize)]
+struct ProfileQuery {
+    /// 期間の最終日; 今日以降は指定できない
+    to: Option<chrono::NaiveDate>,
+    /// この日のPgnLevelを求める; 期間は前日までの30日間。今日まで。toとは同時に指定できない
+    at: Option<chrono::NaiveDate>,
+}
+
+impl ProfileQuery {
+    /// PgnInfoを計算する期間; 省略した場合は今日のPgnLevelの期間。指定が不正な場合はNone
+    fn window(&self, today: chrono::NaiveDate) -> Option<usecase::Window> {
+        match (self.to, self.at) {
+            (Some(to), None) => usecase::Window::ending(to, today).ok(),
+            (None, at) => usecase::Window::at(at.unwrap_or(today), today).ok(),
+            (Some(_), Some(_)) => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CompareQuery {
+    /// カンマ区切りのPgrit ID
+    ids: String,
+}
+
+#[derive(Deserialize)]
+struct LevelStatsQuery {
+    /// 省略した場合は今日; 今日より後の日は指定できない
+    date: Option<chrono::NaiveDate>,
+}
+
+#[derive(Deserialize)]
+struct LeaderboardQuery {
+    office: Option<String>,
+    university: Option<String>,
+    course: Option<String>,
+    /// 新人, アシスタント, ...
+    level: Option<String>,
+    /// H, B, M, D, OB
+    degree_step: Option<String>,
+    page: Option<u64>,
+    per_page: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct RefreshJobsQuery {
+    limit: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -116,11 +284,44 @@ pub struct Config {
     pub pgrit_origin: String,
     pub pgrit_client_key: Arc<str>,
     pub pgrit_client_secret: Arc<str>,
+    /// 定期リフレッシュの間隔(分); 未指定の場合は定期リフレッシュを行わない
+    pub refresh_interval: Option<u64>,
+    /// 定期リフレッシュのcron式(秒から始まる6〜7フィールド); refresh_intervalより優先される
+    pub refresh_cron: Option<String>,
+    /// 定期リフレッシュの実行時刻をずらす最大の秒数
+    #[serde(default)]
+    pub refresh_jitter: u64,
+    /// 待ち受けるアドレス; カンマ区切りで複数指定できる
+    #[serde(default = "default_listen")]
+    pub listen: Vec<SocketAddr>,
+    /// 待ち受けるUnixドメインソケットのパス
+    pub unix_socket: Option<PathBuf>,
+    /// TLSの証明書(PEM)のパス
+    pub tls_cert: Option<PathBuf>,
+    /// TLSの秘密鍵(PEM)のパス
+    pub tls_key: Option<PathBuf>,
+    /// レベルアップを有効にしたユーザについてPgritに投稿するか
+    #[serde(default)]
+    pub announce: bool,
+    /// レベルアップを投稿するbotアカウントのアクセストークン
+    pub pgrit_access_token: Option<String>,
+    /// 投稿文のテンプレート; `{pgrit_id}`, `{from}`, `{to}`, `{pix}`, `{url}`を置き換える
+    pub announce_template: Option<String>,
+    /// 投稿せずに内容を標準出力に書き出すだけにするか
+    #[serde(default)]
+    pub announce_dry_run: bool,
+    /// SlackやDiscordへの通知の設定ファイル(JSON)のパス
+    pub notify_config: Option<PathBuf>,
+}
+
+fn default_listen() -> Vec<SocketAddr> {
+    vec![SocketAddr::from(([0, 0, 0, 0], 3232))]
 }
 
 #[derive(serde::Deserialize)]
 struct PgritOauthQuery {
     code: String,
+    state: Option<String>,
 }
 
 /// Start the server
@@ -133,28 +334,48 @@ pub async fn run(
         pgrit_origin,
         pgrit_client_key,
         pgrit_client_secret,
+        refresh_interval,
+        refresh_cron,
+        refresh_jitter,
+        listen,
+        unix_socket,
+        tls_cert,
+        tls_key,
+        announce,
+        pgrit_access_token,
+        announce_template,
+        announce_dry_run,
+        notify_config,
     }: Config,
 ) {
     const NOT_FOUND: (StatusCode, &str) = (StatusCode::NOT_FOUND, "Not found");
     const INTERNAL_SERVER_ERROR: (StatusCode, &str) =
         (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error");
     const UNAUTHORIZED: (StatusCode, &str) = (StatusCode::UNAUTHORIZED, "Unauthorized");
+    const BAD_REQUEST: (StatusCode, &str) = (StatusCode::BAD_REQUEST, "Bad request");
     const USER_KEY: &str = "user";
 
     let callback_url_ours: Arc<str> = format!("{}/api/auth/pgrit/confirm/", origin).into();
 
-    let pgrit_auth_url: Arc<str> = Url::parse_with_params(
-        &format!("{}/oauth/authorize", pgrit_origin),
-        &[
-            ("client_id", pgrit_client_key.as_ref()),
-            ("response_type", "code"),
-            ("redirect_uri", callback_url_ours.as_ref()),
-            ("scope", "read:accounts"),
-        ],
-    )
-    .unwrap()
-    .as_str()
-    .into();
+    let auth_url = |scope: &str, state: &str| -> Arc<str> {
+        Url::parse_with_params(
+            &format!("{}/oauth/authorize", pgrit_origin),
+            &[
+                ("client_id", pgrit_client_key.as_ref()),
+                ("response_type", "code"),
+                ("redirect_uri", callback_url_ours.as_ref()),
+                ("scope", scope),
+                ("state", state),
+            ],
+        )
+        .unwrap()
+        .as_str()
+        .into()
+    };
+    // 投稿の許可はレベルアップの投稿を自分のアカウントから行う場合のみ別に求める。
+    // コールバックにはセッションのCookieが送られないので、どちらの要求かはstateで区別する
+    let pgrit_auth_url = auth_url(usecase::LOGIN_SCOPE, "login");
+    let pgrit_post_auth_url = auth_url(usecase::POST_SCOPE, POSTING_STATE);
 
     let account_verify_url: Arc<str> = Url::parse(&format!(
         "{}/api/v1/accounts/verify_credentials",
@@ -164,13 +385,36 @@ pub async fn run(
     .as_str()
     .into();
 
-    let session_layer = SessionManagerLayer::new({
-        let store = SqliteStore::new(db.get_sqlite_connection_pool().clone());
-        store.migrate().await.unwrap();
-        store
-    })
-    .with_expiry(Expiry::OnInactivity(Duration::days(7)))
-    .with_secure(origin.starts_with("https://"));
+    let notifiers = Arc::new(Notifiers {
+        announcer: announce.then(|| {
+            announce::Announcer::new(
+                Url::parse(&format!("{}/api/v1/statuses", pgrit_origin)).unwrap(),
+                pgrit_access_token.filter(|t| !t.is_empty()),
+                announce_template,
+                &origin,
+                announce_dry_run,
+            )
+        }),
+        notifier: notify_config
+            .map(|path| notify::Notifier::load(&path).expect("Invalid notification config")),
+    });
+
+    if let Some(schedule) = scheduler::Schedule::new(refresh_interval, refresh_cron.as_deref())
+        .expect("Invalid refresh schedule")
+    {
+        tokio::spawn(scheduler::run(
+            db.clone(),
+            fetch_url.clone(),
+            notifiers.clone(),
+            schedule,
+            std::time::Duration::from_secs(refresh_jitter),
+        ));
+    }
+
+    let session_layer =
+        SessionManagerLayer::new(session_store::DbSessionStore::new(&db).await.unwrap())
+            .with_expiry(Expiry::OnInactivity(Duration::days(7)))
+            .with_secure(origin.starts_with("https://"));
 
     let active_users = get({
         let db = db.clone();
@@ -185,9 +429,12 @@ pub async fn run(
     });
     let profile = get({
         let db = db.clone();
-        |Path(pgrit_id): Path<String>| async move {
+        |Path(pgrit_id): Path<String>, Query(query): Query<ProfileQuery>| async move {
             let now = chrono::Utc::now();
-            match profile(&db, now, &pgrit_id).await {
+            let Some(window) = query.window(now.with_timezone(&Local).date_naive()) else {
+                return Err(BAD_REQUEST);
+            };
+            match usecase::profile_in(&db, now, &pgrit_id, window).await {
                 Ok(Some(profile)) => Ok(json(profile)),
                 Ok(None) => Err(NOT_FOUND),
                 Err(e) => {
@@ -204,12 +451,406 @@ pub async fn run(
                 (StatusCode::TOO_MANY_REQUESTS, "Already running.\n")
             } else {
                 tokio::spawn(async move {
-                    refresh(&db.clone(), &fetch_url.clone()).await;
+                    if let Err(e) = refresh(&db, &fetch_url, &notifiers).await {
+                        eprintln!("{}", e);
+                    }
                 });
                 (StatusCode::OK, "Refresh started.\n")
             }
         }
     });
+    let leaderboard = get({
+        let db = db.clone();
+        |Query(query): Query<LeaderboardQuery>| async move {
+            let filter = usecase::LeaderboardFilter {
+                office: query.office,
+                university: query.university,
+                course: query.course,
+                level: match query.level.map(|l| l.parse()).transpose() {
+                    Ok(level) => level,
+                    Err(_) => return Err(BAD_REQUEST),
+                },
+                degree_step: match query.degree_step.map(|d| d.parse()).transpose() {
+                    Ok(degree_step) => degree_step,
+                    Err(_) => return Err(BAD_REQUEST),
+                },
+            };
+            let page = query.page.unwrap_or(1).max(1);
+            let per_page = query.per_page.unwrap_or(50).clamp(1, 500);
+            if (page - 1).checked_mul(per_page).is_none() {
+                return Err(BAD_REQUEST);
+            }
+            let now = chrono::Utc::now();
+            match usecase::leaderboard(&db, now, &filter, page, per_page).await {
+                Ok(Some(leaderboard)) => Ok(json(leaderboard)),
+                Ok(None) => Err(NOT_FOUND),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    });
+    let group_stats = get({
+        let db = db.clone();
+        |Query(query): Query<GroupStatsQuery>| async move {
+            let now = chrono::Utc::now();
+            match usecase::group_stats(&db, now, query.by).await {
+                Ok(Some(stats)) => Ok(json(stats)),
+                Ok(None) => Err(NOT_FOUND),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    });
+    let compare = get({
+        let db = db.clone();
+        |Query(query): Query<CompareQuery>| async move {
+            let ids = query
+                .ids
+                .split(',')
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(str::to_string)
+                .unique()
+                .collect::<Vec<_>>();
+            if !(2..=MAX_COMPARE_USERS).contains(&ids.len()) {
+                return Err(BAD_REQUEST);
+            }
+            let now = chrono::Utc::now();
+            match usecase::compare(&db, now, &ids).await {
+                Ok(Some(comparison)) => Ok(json(comparison)),
+                Ok(None) => Err(NOT_FOUND),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    });
+    let level_stats = get({
+        let db = db.clone();
+        |Query(query): Query<LevelStatsQuery>| async move {
+            let today = chrono::Utc::now().with_timezone(&Local).date_naive();
+            let date = query.date.unwrap_or(today);
+            if date > today {
+                return Err(BAD_REQUEST);
+            }
+            match usecase::level_stats(&db, date).await {
+                Ok(Some(stats)) => Ok(json(stats)),
+                Ok(None) => Err(NOT_FOUND),
+                Err(Error::InvalidDateRange) => Err(BAD_REQUEST),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    });
+    let cohorts = get({
+        let db = db.clone();
+        || async move {
+            let now = chrono::Utc::now();
+            match usecase::cohorts(&db, now).await {
+                Ok(Some(report)) => Ok(json(report)),
+                Ok(None) => Err(NOT_FOUND),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    });
+    let level_timeline = get({
+        let db = db.clone();
+        |Path(pgrit_id): Path<String>| async move {
+            let now = chrono::Utc::now();
+            match usecase::level_timeline(&db, now, &pgrit_id).await {
+                Ok(Some(timeline)) => Ok(json(timeline)),
+                Ok(None) => Err(NOT_FOUND),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    });
+    let refresh_jobs = get({
+        let db = db.clone();
+        |Query(query): Query<RefreshJobsQuery>| async move {
+            let limit = query.limit.unwrap_or(50).clamp(1, 500);
+            match usecase::refresh_jobs(&db, limit).await {
+                Ok(jobs) => Ok(json(jobs)),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    });
+    let badge = get({
+        let db = db.clone();
+        |Path(file): Path<String>, Query(query): Query<badge::BadgeQuery>| async move {
+            let Some(pgrit_id) = file.strip_suffix(".svg") else {
+                return Err(NOT_FOUND);
+            };
+            // プロフィールを公開していないユーザは存在しないものとして扱う
+            match usecase::is_public(&db, pgrit_id).await {
+                Ok(true) => {}
+                Ok(false) => return Err(NOT_FOUND),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    return Err(INTERNAL_SERVER_ERROR);
+                }
+            }
+            let now = chrono::Utc::now();
+            match usecase::profile(&db, now, pgrit_id).await {
+                Ok(Some(profile)) => Ok((
+                    [
+                        (header::CONTENT_TYPE, "image/svg+xml; charset=utf-8"),
+                        (header::CACHE_CONTROL, "public, max-age=600"),
+                    ],
+                    badge::render(&profile.pgn, &query),
+                )),
+                Ok(None) => Err(NOT_FOUND),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    });
+    let og_image = get({
+        let db = db.clone();
+        |Path(file): Path<String>| async move {
+            let Some(pgrit_id) = file.strip_suffix(".png") else {
+                return Err(NOT_FOUND);
+            };
+            // プロフィールを公開していないユーザは存在しないものとして扱う
+            match usecase::is_public(&db, pgrit_id).await {
+                Ok(true) => {}
+                Ok(false) => return Err(NOT_FOUND),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    return Err(INTERNAL_SERVER_ERROR);
+                }
+            }
+            let now = chrono::Utc::now();
+            let today = now.with_timezone(&Local).date_naive();
+            let window = match usecase::Window::at(today, today) {
+                Ok(window) => window,
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    return Err(INTERNAL_SERVER_ERROR);
+                }
+            };
+            let profile = match usecase::profile_in(&db, now, pgrit_id, window).await {
+                Ok(Some(profile)) => profile,
+                Ok(None) => return Err(NOT_FOUND),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    return Err(INTERNAL_SERVER_ERROR);
+                }
+            };
+            // ラスタライズは重いのでブロッキング用のスレッドで行う
+            match tokio::task::spawn_blocking(move || og::render(&profile, window)).await {
+                Ok(Ok(png)) => Ok((
+                    [
+                        (header::CONTENT_TYPE, "image/png"),
+                        (header::CACHE_CONTROL, "public, max-age=600"),
+                    ],
+                    png,
+                )),
+                Ok(Err(e)) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    });
+    // クローラがプレビューを取得できるよう、公開しているユーザのページはログインなしで返す
+    let profile_page = get({
+        let db = db.clone();
+        let page = static_dir.join("profile/name/index.html");
+        let origin = origin.clone();
+        |Path(pgrit_id): Path<String>, session: Session| async move {
+            let public = match usecase::is_public(&db, &pgrit_id).await {
+                Ok(public) => public,
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    return Err(INTERNAL_SERVER_ERROR);
+                }
+            };
+            // 公開していないユーザのページはログインしている人にのみ、プレビューなしで返す
+            if !public && !matches!(session.get::<user::Model>(USER_KEY).await, Ok(Some(_))) {
+                return Err(NOT_FOUND);
+            }
+            let Ok(html) = tokio::fs::read_to_string(page).await else {
+                return Err(NOT_FOUND);
+            };
+            if !public {
+                return Ok(([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html));
+            }
+            let now = chrono::Utc::now();
+            let html = match usecase::profile(&db, now, &pgrit_id).await {
+                Ok(Some(profile)) => og::inject_meta(&html, &profile, &origin),
+                Ok(None) => html,
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    html
+                }
+            };
+            Ok(([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html))
+        }
+    });
+    let export = |rows: fn(DatabaseConnection, export::Format, export::ExportQuery) -> Body,
+                  format: export::Format| {
+        get({
+            let db = db.clone();
+            move |Query(query): Query<export::ExportQuery>| async move {
+                (
+                    [(header::CONTENT_TYPE, format.content_type())],
+                    rows(db, format, query),
+                )
+            }
+        })
+    };
+    let public_profile = get({
+        let db = db.clone();
+        |session: Session| async move {
+            let Ok(Some(user)) = session.get::<user::Model>(USER_KEY).await else {
+                return Err(UNAUTHORIZED);
+            };
+            match usecase::public_profile(&db, &user.id).await {
+                Ok(setting) => Ok(json(setting)),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    })
+    .put({
+        let db = db.clone();
+        |session: Session, Json(mut setting): Json<public_profile::Model>| async move {
+            let Ok(Some(user)) = session.get::<user::Model>(USER_KEY).await else {
+                return Err(UNAUTHORIZED);
+            };
+            setting.user_id = user.id;
+            match usecase::set_public_profile(&db, setting).await {
+                Ok(setting) => Ok(json(setting)),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    });
+    let announcement = get({
+        let db = db.clone();
+        |session: Session| async move {
+            let Ok(Some(user)) = session.get::<user::Model>(USER_KEY).await else {
+                return Err(UNAUTHORIZED);
+            };
+            match usecase::announcement(&db, &user.id).await {
+                Ok(setting) => Ok(json(setting)),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    })
+    .put({
+        let db = db.clone();
+        |session: Session, Json(mut setting): Json<announcement::Model>| async move {
+            let Ok(Some(user)) = session.get::<user::Model>(USER_KEY).await else {
+                return Err(UNAUTHORIZED);
+            };
+            setting.user_id = user.id;
+            match usecase::set_announcement(&db, setting).await {
+                Ok(setting) => Ok(json(setting)),
+                Err(Error::MissingScope(_)) => Err((
+                    StatusCode::FORBIDDEN,
+                    "Posting is not authorized; visit /api/auth/pgrit/posting/ first",
+                )),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    });
+    let goals = get({
+        let db = db.clone();
+        |session: Session| async move {
+            let Ok(Some(user)) = session.get::<user::Model>(USER_KEY).await else {
+                return Err(UNAUTHORIZED);
+            };
+            match usecase::goals(&db, &user.id).await {
+                Ok(goals) => Ok(json(goals)),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    })
+    .post({
+        let db = db.clone();
+        |session: Session, Json(goal): Json<goal::Model>| async move {
+            let Ok(Some(user)) = session.get::<user::Model>(USER_KEY).await else {
+                return Err(UNAUTHORIZED);
+            };
+            match usecase::create_goal(&db, chrono::Utc::now(), &user.id, goal).await {
+                Ok(goal) => Ok((StatusCode::CREATED, json(goal))),
+                Err(Error::InvalidGoal(reason)) => Err((StatusCode::BAD_REQUEST, reason)),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    });
+    let goal = put({
+        let db = db.clone();
+        |session: Session, Path(id): Path<i32>, Json(goal): Json<goal::Model>| async move {
+            let Ok(Some(user)) = session.get::<user::Model>(USER_KEY).await else {
+                return Err(UNAUTHORIZED);
+            };
+            match usecase::update_goal(&db, chrono::Utc::now(), &user.id, id, goal).await {
+                Ok(Some(goal)) => Ok(json(goal)),
+                Ok(None) => Err(NOT_FOUND),
+                Err(Error::InvalidGoal(reason)) => Err((StatusCode::BAD_REQUEST, reason)),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    })
+    .delete({
+        let db = db.clone();
+        |session: Session, Path(id): Path<i32>| async move {
+            let Ok(Some(user)) = session.get::<user::Model>(USER_KEY).await else {
+                return Err(UNAUTHORIZED);
+            };
+            match usecase::delete_goal(&db, &user.id, id).await {
+                Ok(true) => Ok(StatusCode::NO_CONTENT),
+                Ok(false) => Err(NOT_FOUND),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    Err(INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+    });
     let me = get({
         |session: Session| async move { json(session.get::<user::Model>(USER_KEY).await.ok().flatten()) }
     });
@@ -235,6 +876,22 @@ pub async fn run(
                     }
                 }),
             )
+            .route(
+                "/posting/",
+                get({
+                    let pgrit_post_auth_url = pgrit_post_auth_url.clone();
+                    |session: Session| async move {
+                        if !matches!(session.get::<user::Model>(USER_KEY).await, Ok(Some(_))) {
+                            return UNAUTHORIZED.into_response();
+                        }
+                        if session.insert(INITIATED_KEY, true).await.is_err() {
+                            INTERNAL_SERVER_ERROR.into_response()
+                        } else {
+                            Redirect::temporary(&pgrit_post_auth_url).into_response()
+                        }
+                    }
+                }),
+            )
             .route(
                 "/confirm/",
                 get({
@@ -249,6 +906,10 @@ pub async fn run(
                             return UNAUTHORIZED.into_response();
                         }
                         session.remove::<bool>(INITIATED_KEY).await.unwrap();
+                        let scope = match query.state.as_deref() {
+                            Some(POSTING_STATE) => usecase::POST_SCOPE,
+                            _ => usecase::LOGIN_SCOPE,
+                        };
 
                         let code = query.code;
 
@@ -262,6 +923,7 @@ pub async fn run(
                             &pgrit_client_key,
                             &pgrit_client_secret,
                             &code,
+                            scope,
                         )
                         .await
                         {
@@ -303,6 +965,30 @@ pub async fn run(
                 .route("/", health_check)
                 .route("/actives.json", active_users)
                 .route("/profile/pgrit/:pgrit_id/data.json", profile.clone()) // <- 暫定, 本当は /profile/{pgrit_id}.json にしたい
+                .route("/profile/pgrit/:pgrit_id/levels.json", level_timeline)
+                .route("/leaderboard.json", leaderboard)
+                .route("/compare.json", compare)
+                .route("/stats/groups.json", group_stats)
+                .route("/stats/levels.json", level_stats)
+                .route("/stats/cohorts.json", cohorts)
+                .route("/refresh/jobs.json", refresh_jobs)
+                .route("/me/announcement.json", announcement)
+                .route("/me/public_profile.json", public_profile)
+                .route("/me/goals", goals)
+                .route("/me/goals/:id", goal)
+                .route("/export/pix.csv", export(export::pix, export::Format::Csv))
+                .route(
+                    "/export/pix.ndjson",
+                    export(export::pix, export::Format::Ndjson),
+                )
+                .route(
+                    "/export/students.csv",
+                    export(export::students, export::Format::Csv),
+                )
+                .route(
+                    "/export/students.ndjson",
+                    export(export::students, export::Format::Ndjson),
+                )
                 .route(
                     "/auth/logout/",
                     get({
@@ -317,15 +1003,14 @@ pub async fn run(
                 .route("/refresh/", refresh)
                 .nest("/auth/pgrit/", pgrit_oauth_router),
         )
+        .route("/badge/:file", badge)
+        .route("/og/:file", og_image)
         .nest(
             "/profile/:pgrid_id/",
             Router::new()
-                .route_service(
-                    "/",
-                    ServeFile::new(static_dir.join("profile/name/index.html")),
-                )
                 .route("/data.json", profile)
-                .layer(block_unauthorized),
+                .layer(block_unauthorized)
+                .route("/", profile_page),
         )
         .nest_service(
             "/",
@@ -335,6 +1020,19 @@ pub async fn run(
         .layer(session_layer)
         .fallback(NOT_FOUND);
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3232").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let tls = match (tls_cert, tls_key) {
+        (Some(cert), Some(key)) => Some((cert, key)),
+        (None, None) => None,
+        _ => panic!("Both TLS_CERT and TLS_KEY must be set to enable TLS"),
+    };
+    listener::serve(
+        app,
+        listener::Listen {
+            addrs: listen,
+            unix_socket,
+            tls,
+        },
+    )
+    .await
+    .unwrap();
 }
@@ -0,0 +1,82 @@
+//! 設定された待ち受け先(TCP, TLS, Unixドメインソケット)でサーバを起動する
+
+use std::{net::SocketAddr, os::unix::fs::FileTypeExt, path::PathBuf};
+
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+    service::TowerToHyperService,
+};
+use tokio::{net::UnixListener, task::JoinSet};
+
+/// 待ち受けの設定
+pub struct Listen {
+    /// TCPで待ち受けるアドレス
+    pub addrs: Vec<SocketAddr>,
+    /// Unixドメインソケットのパス
+    pub unix_socket: Option<PathBuf>,
+    /// TLSの証明書と秘密鍵のパス; 指定された場合TCPはHTTPSで待ち受ける
+    pub tls: Option<(PathBuf, PathBuf)>,
+}
+
+/// 全ての待ち受け先でサーバを起動し、いずれかが終了するまで待つ
+pub async fn serve(app: Router, listen: Listen) -> std::io::Result<()> {
+    let tls = match listen.tls {
+        Some((cert, key)) => Some(RustlsConfig::from_pem_file(cert, key).await?),
+        None => None,
+    };
+
+    let mut servers = JoinSet::new();
+    for addr in listen.addrs {
+        let app = app.clone();
+        if let Some(tls) = tls.clone() {
+            servers.spawn(async move {
+                axum_server::bind_rustls(addr, tls)
+                    .serve(app.into_make_service())
+                    .await
+            });
+        } else {
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            servers.spawn(async move { axum::serve(listener, app).await });
+        }
+    }
+    if let Some(path) = listen.unix_socket {
+        // 前回起動時のソケットが残っている場合は削除する。ソケット以外のファイルは消さない
+        match std::fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(&path)?,
+            Ok(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                ))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let listener = UnixListener::bind(&path)?;
+        servers.spawn(serve_unix(listener, app));
+    }
+
+    match servers.join_next().await {
+        Some(result) => result.map_err(std::io::Error::other)?,
+        None => Ok(()),
+    }
+}
+
+/// Unixドメインソケットで待ち受ける
+async fn serve_unix(listener: UnixListener, app: Router) -> std::io::Result<()> {
+    loop {
+        let (socket, _) = listener.accept().await?;
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(socket), service)
+                .await
+            {
+                eprintln!("{}", e);
+            }
+        });
+    }
+}
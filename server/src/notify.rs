@@ -0,0 +1,876 @@
+//! SlackやDiscordのWebhookへの通知。
+//! レベルアップ・降格の危険・連続記録の途切れ・週間のまとめを、学生情報のSlack ID・Discord IDでメンションして送る。
+//! 送信先は`Sender`を実装すれば差し替えられる
+
+use std::{collections::HashSet, path::Path};
+
+use async_trait::async_trait;
+use chrono::{Datelike, Local, NaiveDate, Weekday};
+use entity::{error::Error, pgn_level::PgnLevel, season, student, user};
+use itertools::Itertools;
+use sea_orm::{prelude::DateTimeUtc, DatabaseConnection};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::usecase::{self, LevelUpdate, LEVEL_WINDOW_DAYS};
+
+/// 通知の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// レベルアップ
+    LevelUp,
+    /// 今日PIXを得ないと明日降格する
+    LevelDownRisk,
+    /// 連続でPIXを得ていた記録が途切れた
+    StreakBroken,
+    /// 週間のまとめ
+    WeeklySummary,
+}
+
+impl EventKind {
+    const ALL: [EventKind; 4] = [
+        EventKind::LevelUp,
+        EventKind::LevelDownRisk,
+        EventKind::StreakBroken,
+        EventKind::WeeklySummary,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::LevelUp => "level_up",
+            EventKind::LevelDownRisk => "level_down_risk",
+            EventKind::StreakBroken => "streak_broken",
+            EventKind::WeeklySummary => "weekly_summary",
+        }
+    }
+}
+
+/// 送信先の種類
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SenderKind {
+    Slack,
+    Discord,
+}
+
+/// 送信先の設定
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChannelConfig {
+    pub kind: SenderKind,
+    /// Incoming WebhookのURL
+    pub webhook_url: String,
+    /// 送る通知の種類; 省略した場合は全て
+    #[serde(default = "all_events")]
+    pub events: Vec<EventKind>,
+}
+
+fn all_events() -> Vec<EventKind> {
+    EventKind::ALL.to_vec()
+}
+
+/// 通知の設定
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotifyConfig {
+    pub channels: Vec<ChannelConfig>,
+    /// 週間のまとめを送る曜日
+    #[serde(default = "default_summary_day")]
+    pub weekly_summary_day: Weekday,
+    /// 週間のまとめに載せる人数
+    #[serde(default = "default_summary_top")]
+    pub summary_top: usize,
+    /// 途切れたことを通知する連続記録の最小日数
+    #[serde(default = "default_streak_days")]
+    pub streak_days: u32,
+}
+
+fn default_summary_day() -> Weekday {
+    Weekday::Mon
+}
+
+fn default_summary_top() -> usize {
+    5
+}
+
+fn default_streak_days() -> u32 {
+    3
+}
+
+/// 通知でメンションするメンバー
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub user_id: String,
+    pub pgrit_id: String,
+    pub slack_id: Option<String>,
+    pub discord_id: Option<String>,
+}
+
+impl Member {
+    fn new(user: &user::Model, student: Option<&student::Model>) -> Self {
+        Member {
+            user_id: user.id.clone(),
+            pgrit_id: user.pgrit_id.clone(),
+            slack_id: student
+                .map(|s| s.slack_id.clone())
+                .filter(|id| !id.is_empty()),
+            discord_id: student
+                .and_then(|s| s.discord_id.clone())
+                .filter(|id| !id.is_empty()),
+        }
+    }
+}
+
+/// 通知する出来事
+#[derive(Debug, Clone)]
+pub enum Event {
+    LevelUp {
+        member: Member,
+        from: PgnLevel,
+        to: PgnLevel,
+        last_month: u32,
+    },
+    LevelDownRisk {
+        member: Member,
+        level: PgnLevel,
+        /// 今日必要なPIX
+        required: u32,
+    },
+    StreakBroken {
+        member: Member,
+        /// 途切れるまでの連続日数
+        days: u32,
+    },
+    WeeklySummary {
+        /// 期間の初日
+        from: NaiveDate,
+        /// 期間の最終日
+        to: NaiveDate,
+        /// 全員の合計PIX
+        total: u32,
+        /// PIXの多い順のメンバー
+        top: Vec<(Member, u32)>,
+    },
+}
+
+impl Event {
+    fn kind(&self) -> EventKind {
+        match self {
+            Event::LevelUp { .. } => EventKind::LevelUp,
+            Event::LevelDownRisk { .. } => EventKind::LevelDownRisk,
+            Event::StreakBroken { .. } => EventKind::StreakBroken,
+            Event::WeeklySummary { .. } => EventKind::WeeklySummary,
+        }
+    }
+
+    /// 重複して送らないための記録のキー
+    fn key(&self) -> (String, &str) {
+        match self {
+            Event::LevelUp { member, to, .. } => {
+                (format!("{}:{}", self.kind().as_str(), to), &member.user_id)
+            }
+            Event::LevelDownRisk { member, .. } | Event::StreakBroken { member, .. } => {
+                (self.kind().as_str().to_string(), &member.user_id)
+            }
+            Event::WeeklySummary { .. } => (self.kind().as_str().to_string(), ""),
+        }
+    }
+
+    /// 送信先に合わせたメンションでメッセージを組み立てる
+    fn text(&self, sender: &dyn Sender) -> String {
+        match self {
+            Event::LevelUp {
+                member,
+                from,
+                to,
+                last_month,
+            } => format!(
+                ":tada: {} のPgnLevelが {} から {} に上がりました！ (月間 {} PIX)",
+                sender.mention(member),
+                from,
+                to,
+                last_month
+            ),
+            Event::LevelDownRisk {
+                member,
+                level,
+                required,
+            } => format!(
+                ":warning: {} 今日 {} PIX以上獲得しないと、明日 {} から降格します",
+                sender.mention(member),
+                required,
+                level
+            ),
+            Event::StreakBroken { member, days } => format!(
+                ":broken_heart: {} の{}日間続いていたPIXの連続記録が途切れました",
+                sender.mention(member),
+                days
+            ),
+            Event::WeeklySummary {
+                from,
+                to,
+                total,
+                top,
+            } => {
+                let ranking = top
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (member, pix))| {
+                        format!("{}. {} {} PIX", i + 1, sender.mention(member), pix)
+                    })
+                    .join("\n");
+                format!(
+                    ":bar_chart: {} 〜 {} の週間まとめ: 合計 {} PIX\n{}",
+                    from, to, total, ranking
+                )
+            }
+        }
+    }
+}
+
+/// 通知の送信先
+#[async_trait]
+pub trait Sender: Send + Sync {
+    /// メンバーへのメンション; IDが未登録の場合はPgrit IDを用いる
+    fn mention(&self, member: &Member) -> String;
+
+    /// メッセージを送る
+    async fn send(&self, text: &str) -> Result<(), reqwest::Error>;
+}
+
+/// SlackのIncoming Webhook
+pub struct SlackWebhook {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl SlackWebhook {
+    pub fn new(url: String, client: reqwest::Client) -> Self {
+        SlackWebhook { url, client }
+    }
+}
+
+#[async_trait]
+impl Sender for SlackWebhook {
+    fn mention(&self, member: &Member) -> String {
+        match &member.slack_id {
+            Some(id) => format!("<@{}>", id),
+            None => member.pgrit_id.clone(),
+        }
+    }
+
+    async fn send(&self, text: &str) -> Result<(), reqwest::Error> {
+        self.client
+            .post(&self.url)
+            .json(&json!({ "text": text }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// DiscordのWebhook
+pub struct DiscordWebhook {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl DiscordWebhook {
+    pub fn new(url: String, client: reqwest::Client) -> Self {
+        DiscordWebhook { url, client }
+    }
+}
+
+#[async_trait]
+impl Sender for DiscordWebhook {
+    fn mention(&self, member: &Member) -> String {
+        match &member.discord_id {
+            Some(id) => format!("<@{}>", id),
+            None => member.pgrit_id.clone(),
+        }
+    }
+
+    async fn send(&self, text: &str) -> Result<(), reqwest::Error> {
+        self.client
+            .post(&self.url)
+            .json(&json!({
+                "content": text,
+                "allowed_mentions": { "parse": ["users"] },
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+struct Channel {
+    sender: Box<dyn Sender>,
+    events: HashSet<EventKind>,
+}
+
+/// 設定された送信先に通知を送る
+pub struct Notifier {
+    channels: Vec<Channel>,
+    weekly_summary_day: Weekday,
+    summary_top: usize,
+    streak_days: u32,
+}
+
+impl Notifier {
+    pub fn new(config: NotifyConfig) -> Self {
+        let client = reqwest::Client::new();
+        let channels = config
+            .channels
+            .into_iter()
+            .map(|c| Channel {
+                sender: match c.kind {
+                    SenderKind::Slack => Box::new(SlackWebhook::new(c.webhook_url, client.clone()))
+                        as Box<dyn Sender>,
+                    SenderKind::Discord => {
+                        Box::new(DiscordWebhook::new(c.webhook_url, client.clone()))
+                    }
+                },
+                events: c.events.into_iter().collect(),
+            })
+            .collect();
+        Notifier {
+            channels,
+            weekly_summary_day: config.weekly_summary_day,
+            summary_top: config.summary_top,
+            streak_days: config.streak_days,
+        }
+    }
+
+    /// 任意の送信先に全ての種類の通知を送る。
+    /// 曜日・人数・日数は設定ファイルを省略した場合と同じ既定値を用いる
+    pub fn with_senders(senders: Vec<Box<dyn Sender>>) -> Self {
+        Notifier {
+            channels: senders
+                .into_iter()
+                .map(|sender| Channel {
+                    sender,
+                    events: EventKind::ALL.into_iter().collect(),
+                })
+                .collect(),
+            weekly_summary_day: default_summary_day(),
+            summary_top: default_summary_top(),
+            streak_days: default_streak_days(),
+        }
+    }
+
+    /// JSONの設定ファイルから読み込む
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let config = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(Notifier::new(config))
+    }
+
+    fn subscribed(&self, kind: EventKind) -> bool {
+        self.channels.iter().any(|c| c.events.contains(&kind))
+    }
+
+    /// リフレッシュの結果から通知する出来事を集める
+    async fn events(
+        &self,
+        db: &DatabaseConnection,
+        now: DateTimeUtc,
+        updates: &[LevelUpdate],
+    ) -> Result<Vec<Event>, Error> {
+        let Some(users) = usecase::active_users(db).await? else {
+            return Ok(Vec::new());
+        };
+        let students = usecase::students(db, users.iter().map(|u| u.id.clone())).await?;
+        let member = |user: &user::Model| Member::new(user, students.get(&user.id));
+
+        // 今日のデータは含めない
+        let today = now.with_timezone(&Local).date_naive();
+        let yesterday = today - chrono::Duration::days(1);
+        let window_start = today - chrono::Duration::days(LEVEL_WINDOW_DAYS);
+        let daily = usecase::daily_pix(db, window_start, today).await?;
+        let seasons = usecase::seasons(db).await?;
+        let thresholds = season::thresholds_at(&seasons, yesterday);
+        // 明日のPgnLevelは今日に有効な閾値で決まる
+        let next_thresholds = season::thresholds_at(&seasons, today);
+        let amount = |user: &user::Model, date: NaiveDate| {
+            daily
+                .get(&user.id)
+                .and_then(|d| d.get(&date))
+                .copied()
+                .unwrap_or(0)
+        };
+
+        let mut events = Vec::new();
+        if self.subscribed(EventKind::LevelUp) {
+            events.extend(
+                updates
+                    .iter()
+                    .filter(|u| u.to > u.from)
+                    .map(|u| Event::LevelUp {
+                        member: member(&u.user),
+                        from: u.from,
+                        to: u.to,
+                        last_month: u.last_month,
+                    }),
+            );
+        }
+        for user in &users {
+            let last_month = daily.get(&user.id).map(|d| d.values().sum()).unwrap_or(0);
+            let level = thresholds.level(last_month);
+
+            // 明日には期間の初日のPIXが外れる
+            let remaining = last_month - amount(user, window_start);
+            let min_pix = next_thresholds.min_pix(level);
+            if self.subscribed(EventKind::LevelDownRisk)
+                && level > PgnLevel::Iron
+                && remaining < min_pix
+            {
+                events.push(Event::LevelDownRisk {
+                    member: member(user),
+                    level,
+                    required: min_pix - remaining,
+                });
+            }
+
+            if self.subscribed(EventKind::StreakBroken) && amount(user, yesterday) == 0 {
+                let days = (2..=LEVEL_WINDOW_DAYS)
+                    .map(|d| amount(user, today - chrono::Duration::days(d)))
+                    .take_while(|a| *a > 0)
+                    .count() as u32;
+                if days >= self.streak_days {
+                    events.push(Event::StreakBroken {
+                        member: member(user),
+                        days,
+                    });
+                }
+            }
+        }
+
+        if self.subscribed(EventKind::WeeklySummary) && today.weekday() == self.weekly_summary_day {
+            let from = today - chrono::Duration::days(7);
+            let week = users
+                .iter()
+                .map(|user| {
+                    let pix = daily
+                        .get(&user.id)
+                        .map(|d| d.range(from..today).map(|(_, a)| a).sum())
+                        .unwrap_or(0);
+                    (user, pix)
+                })
+                .collect_vec();
+            let total = week.iter().map(|(_, pix)| pix).sum();
+            let top = week
+                .into_iter()
+                .sorted_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.pgrit_id.cmp(&b.0.pgrit_id)))
+                .take(self.summary_top)
+                .map(|(user, pix)| (member(user), pix))
+                .collect();
+            events.push(Event::WeeklySummary {
+                from,
+                to: yesterday,
+                total,
+                top,
+            });
+        }
+        Ok(events)
+    }
+
+    /// リフレッシュの結果を通知する。
+    /// 同じ日に同じ通知を送らないよう送信を記録し、1件の送信の失敗はログに残して続行する。
+    /// どの送信先にも送れなかった通知は記録を取り消し、次のリフレッシュで送り直す
+    pub async fn notify(
+        &self,
+        db: &DatabaseConnection,
+        now: DateTimeUtc,
+        updates: &[LevelUpdate],
+    ) -> Result<(), Error> {
+        let today = now.with_timezone(&Local).date_naive();
+        for event in self.events(db, now, updates).await? {
+            let (kind, user_id) = event.key();
+            // 並行して送らないよう、送信の前に記録しておく
+            if !usecase::mark_notified(db, now, &kind, user_id, today).await? {
+                continue;
+            }
+            let mut sent = false;
+            for channel in self
+                .channels
+                .iter()
+                .filter(|c| c.events.contains(&event.kind()))
+            {
+                let text = event.text(channel.sender.as_ref());
+                match channel.sender.send(&text).await {
+                    Ok(()) => sent = true,
+                    Err(e) => eprintln!("Failed to send a notification: {}", e),
+                }
+            }
+            if !sent {
+                usecase::unmark_notified(db, &kind, user_id, today).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{
+        extract::{Path, State},
+        routing::post,
+        Json, Router,
+    };
+    use chrono::{TimeZone, Utc};
+    use entity::{pix, refreshed_users};
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{Database, EntityTrait, Set};
+    use serde_json::Value;
+
+    use super::*;
+
+    /// Webhookが受け取ったリクエスト; (パス, 本文)
+    type Received = Arc<Mutex<Vec<(String, Value)>>>;
+
+    /// 受け取ったJSONを記録するだけのWebhookを立て、そのオリジンを返す
+    async fn stand_in() -> (String, Received) {
+        let received = Received::default();
+        let app = Router::new()
+            .route(
+                "/:path",
+                post(
+                    |State(received): State<Received>,
+                     Path(path): Path<String>,
+                     Json(body): Json<Value>| async move {
+                        received.lock().unwrap().push((path, body));
+                        "ok"
+                    },
+                ),
+            )
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let origin = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (origin, received)
+    }
+
+    /// 送ったメッセージを記録する送信先
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    #[async_trait]
+    impl Sender for Recorder {
+        fn mention(&self, member: &Member) -> String {
+            format!("@{}", member.pgrit_id)
+        }
+
+        async fn send(&self, text: &str) -> Result<(), reqwest::Error> {
+            self.0.lock().unwrap().push(text.to_string());
+            Ok(())
+        }
+    }
+
+    fn member(slack_id: Option<&str>, discord_id: Option<&str>) -> Member {
+        Member {
+            user_id: "0xa".to_string(),
+            pgrit_id: "alice".to_string(),
+            slack_id: slack_id.map(str::to_string),
+            discord_id: discord_id.map(str::to_string),
+        }
+    }
+
+    /// 最新のリフレッシュに`users`だけが含まれるデータベース
+    async fn setup(users: &[user::Model]) -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        let ulid = ulid::Ulid::new().to_string();
+        for user in users {
+            user::Entity::insert(user::ActiveModel::from(user.clone()))
+                .exec(&db)
+                .await
+                .unwrap();
+            refreshed_users::Entity::insert(refreshed_users::ActiveModel {
+                ulid: Set(ulid.clone()),
+                user_id: Set(user.id.clone()),
+            })
+            .exec(&db)
+            .await
+            .unwrap();
+        }
+        db
+    }
+
+    async fn insert_pix(db: &DatabaseConnection, user: &user::Model, pix: &[(&str, i32)]) {
+        for (date, amount) in pix {
+            pix::Entity::insert(pix::ActiveModel {
+                date: Set(date.parse().unwrap()),
+                user_id: Set(user.id.clone()),
+                amount: Set(*amount),
+            })
+            .exec(db)
+            .await
+            .unwrap();
+        }
+    }
+
+    fn user(id: &str, pgrit_id: &str) -> user::Model {
+        user::Model {
+            id: id.to_string(),
+            pgrit_id: pgrit_id.to_string(),
+        }
+    }
+
+    fn alice() -> user::Model {
+        user("0xa", "alice")
+    }
+
+    fn bob() -> user::Model {
+        user("0xb", "bob")
+    }
+
+    /// 通知する出来事を`Recorder`向けの文面にして並べる
+    async fn event_texts(
+        notifier: &Notifier,
+        db: &DatabaseConnection,
+        now: DateTimeUtc,
+    ) -> Vec<String> {
+        let recorder = Recorder(Default::default());
+        notifier
+            .events(db, now, &[])
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.text(&recorder))
+            .sorted()
+            .collect()
+    }
+
+    fn level_up(user: user::Model) -> LevelUpdate {
+        LevelUpdate {
+            user,
+            from: PgnLevel::Iron,
+            to: PgnLevel::Bronze,
+            last_month: 1234,
+        }
+    }
+
+    /// 週間のまとめを送る既定の月曜日以外の日
+    fn tuesday() -> DateTimeUtc {
+        Utc.with_ymd_and_hms(2024, 5, 14, 3, 0, 0).unwrap()
+    }
+
+    fn only(kind: EventKind) -> Notifier {
+        let mut notifier = Notifier::with_senders(vec![Box::new(Recorder(Default::default()))]);
+        notifier.channels[0].events = HashSet::from([kind]);
+        notifier
+    }
+
+    #[tokio::test]
+    async fn level_down_risk_when_remaining_pix_falls_below_threshold() {
+        let db = setup(&[alice(), bob()]).await;
+        // どちらも600 PIXでBronze; 明日には期間の初日の4/14の分が外れる
+        insert_pix(&db, &alice(), &[("2024-04-14", 100), ("2024-05-01", 500)]).await;
+        insert_pix(&db, &bob(), &[("2024-04-14", 101), ("2024-05-01", 499)]).await;
+
+        assert_eq!(
+            event_texts(&only(EventKind::LevelDownRisk), &db, tuesday()).await,
+            [":warning: @bob 今日 1 PIX以上獲得しないと、明日 Bronze から降格します"]
+        );
+    }
+
+    #[tokio::test]
+    async fn level_down_risk_uses_thresholds_for_tomorrow() {
+        let db = setup(&[alice()]).await;
+        insert_pix(&db, &alice(), &[("2024-05-01", 600)]).await;
+        // 今日から有効なシーズンの閾値が明日のPgnLevelに用いられる
+        season::Entity::insert(season::ActiveModel {
+            name: Set("test".to_string()),
+            effective_from: Set("2024-05-14".parse().unwrap()),
+            bronze_min: Set(700),
+            silver_min: Set(1000),
+            gold_min: Set(2500),
+            platinum_min: Set(5000),
+            diamond_min: Set(10000),
+            master_min: Set(20000),
+            grandmaster_min: Set(35000),
+            ..Default::default()
+        })
+        .exec(&db)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            event_texts(&only(EventKind::LevelDownRisk), &db, tuesday()).await,
+            [":warning: @alice 今日 100 PIX以上獲得しないと、明日 Bronze から降格します"]
+        );
+    }
+
+    #[tokio::test]
+    async fn streak_broken_after_configured_days() {
+        let carol = user("0xc", "carol");
+        let db = setup(&[alice(), bob(), carol.clone()]).await;
+        let streak = [("2024-05-10", 1), ("2024-05-11", 1), ("2024-05-12", 1)];
+        insert_pix(&db, &alice(), &streak).await;
+        // 2日間では通知しない
+        insert_pix(&db, &bob(), &streak[1..]).await;
+        // 昨日もPIXを得ていれば途切れていない
+        insert_pix(&db, &carol, &streak).await;
+        insert_pix(&db, &carol, &[("2024-05-13", 1)]).await;
+
+        assert_eq!(
+            event_texts(&only(EventKind::StreakBroken), &db, tuesday()).await,
+            [":broken_heart: @alice の3日間続いていたPIXの連続記録が途切れました"]
+        );
+    }
+
+    #[tokio::test]
+    async fn weekly_summary_on_configured_day() {
+        let db = setup(&[alice(), bob()]).await;
+        // 前週の月曜日から日曜日までが期間; 期間外と今日の分は含めない
+        insert_pix(
+            &db,
+            &alice(),
+            &[("2024-05-05", 1000), ("2024-05-06", 10), ("2024-05-12", 20)],
+        )
+        .await;
+        insert_pix(&db, &bob(), &[("2024-05-08", 30), ("2024-05-13", 1000)]).await;
+        let monday = Utc.with_ymd_and_hms(2024, 5, 13, 3, 0, 0).unwrap();
+
+        let notifier = only(EventKind::WeeklySummary);
+        assert_eq!(
+            event_texts(&notifier, &db, monday).await,
+            [":bar_chart: 2024-05-06 〜 2024-05-12 の週間まとめ: 合計 60 PIX\n1. @alice 30 PIX\n2. @bob 30 PIX"]
+        );
+        assert!(event_texts(&notifier, &db, tuesday()).await.is_empty());
+    }
+
+    #[test]
+    fn event_keys_dedupe_per_kind_and_user() {
+        let level_up = |to| Event::LevelUp {
+            member: member(None, None),
+            from: PgnLevel::Iron,
+            to,
+            last_month: 0,
+        };
+        // 同じ日でも別のレベルへのレベルアップは別の通知
+        assert_eq!(
+            level_up(PgnLevel::Bronze).key(),
+            ("level_up:Bronze".to_string(), "0xa")
+        );
+        assert_eq!(
+            level_up(PgnLevel::Silver).key(),
+            ("level_up:Silver".to_string(), "0xa")
+        );
+        let risk = Event::LevelDownRisk {
+            member: member(None, None),
+            level: PgnLevel::Bronze,
+            required: 1,
+        };
+        assert_eq!(risk.key(), ("level_down_risk".to_string(), "0xa"));
+        let streak = Event::StreakBroken {
+            member: member(None, None),
+            days: 3,
+        };
+        assert_eq!(streak.key(), ("streak_broken".to_string(), "0xa"));
+        // ユーザに紐付かない通知
+        let summary = Event::WeeklySummary {
+            from: "2024-05-06".parse().unwrap(),
+            to: "2024-05-12".parse().unwrap(),
+            total: 0,
+            top: Vec::new(),
+        };
+        assert_eq!(summary.key(), ("weekly_summary".to_string(), ""));
+    }
+
+    #[tokio::test]
+    async fn webhooks_post_json_with_mentions() {
+        let (origin, received) = stand_in().await;
+        let client = reqwest::Client::new();
+        let slack = SlackWebhook::new(format!("{}/slack", origin), client.clone());
+        let discord = DiscordWebhook::new(format!("{}/discord", origin), client);
+
+        assert_eq!(slack.mention(&member(Some("U1"), None)), "<@U1>");
+        assert_eq!(discord.mention(&member(None, Some("42"))), "<@42>");
+        // IDが未登録の場合はPgrit ID
+        assert_eq!(slack.mention(&member(None, Some("42"))), "alice");
+        assert_eq!(discord.mention(&member(Some("U1"), None)), "alice");
+
+        slack.send("hello").await.unwrap();
+        discord.send("hello").await.unwrap();
+        assert_eq!(
+            *received.lock().unwrap(),
+            [
+                ("slack".to_string(), json!({ "text": "hello" })),
+                (
+                    "discord".to_string(),
+                    json!({ "content": "hello", "allowed_mentions": { "parse": ["users"] } })
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn webhook_reports_error_status() {
+        let (origin, _) = stand_in().await;
+        let slack = SlackWebhook::new(format!("{}/a/b", origin), reqwest::Client::new());
+        assert!(slack.send("hello").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn custom_senders_are_notified_once_a_day() {
+        let db = setup(&[alice()]).await;
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let notifier = Notifier::with_senders(vec![Box::new(Recorder(sent.clone()))]);
+
+        let updates = [level_up(alice())];
+        notifier.notify(&db, tuesday(), &updates).await.unwrap();
+        notifier.notify(&db, tuesday(), &updates).await.unwrap();
+
+        assert_eq!(
+            *sent.lock().unwrap(),
+            [":tada: @alice のPgnLevelが Iron から Bronze に上がりました！ (月間 1234 PIX)"]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_notifications_are_sent_again() {
+        let db = setup(&[alice()]).await;
+        let (origin, _) = stand_in().await;
+        let updates = [level_up(alice())];
+        // 存在しないパスへの送信は失敗する
+        let broken = SlackWebhook::new(format!("{}/a/b", origin), reqwest::Client::new());
+        Notifier::with_senders(vec![Box::new(broken)])
+            .notify(&db, tuesday(), &updates)
+            .await
+            .unwrap();
+
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        Notifier::with_senders(vec![Box::new(Recorder(sent.clone()))])
+            .notify(&db, tuesday(), &updates)
+            .await
+            .unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn channels_receive_only_configured_events() {
+        let db = setup(&[alice()]).await;
+        let (origin, received) = stand_in().await;
+        let config = serde_json::from_value(json!({
+            "channels": [
+                { "kind": "slack", "webhook_url": format!("{}/slack", origin), "events": ["level_up"] },
+                { "kind": "discord", "webhook_url": format!("{}/discord", origin), "events": ["weekly_summary"] },
+            ],
+        }))
+        .unwrap();
+        Notifier::new(config)
+            .notify(&db, tuesday(), &[level_up(alice())])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *received.lock().unwrap(),
+            [(
+                "slack".to_string(),
+                json!({ "text": ":tada: alice のPgnLevelが Iron から Bronze に上がりました！ (月間 1234 PIX)" })
+            )]
+        );
+    }
+}
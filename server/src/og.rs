@@ -0,0 +1,284 @@
+//! SlackやDiscord、Mastodonでプロフィールのリンクを共有した際のOpenGraphのプレビュー。
+//! 画像はSVGで組み立ててからresvgでPNGにラスタライズする
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+};
+
+use chrono::NaiveDate;
+use entity::user_profile::UserProfile;
+use itertools::Itertools;
+use resvg::{tiny_skia, usvg};
+
+use crate::{
+    badge::{color, escape, rainbow},
+    usecase::Window,
+};
+
+const WIDTH: u32 = 1200;
+const HEIGHT: u32 = 630;
+const FONT_FAMILY: &str = "Noto Sans CJK JP, Noto Sans JP, DejaVu Sans, sans-serif";
+
+/// sparklineを描く領域
+const CHART_LEFT: f32 = 80.0;
+const CHART_RIGHT: f32 = 1120.0;
+const CHART_TOP: f32 = 420.0;
+const CHART_BOTTOM: f32 = 570.0;
+
+/// システムのフォント; 読み込みに時間がかかるので一度だけ読み込む
+fn fontdb() -> Arc<usvg::fontdb::Database> {
+    static FONTDB: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+    FONTDB
+        .get_or_init(|| {
+            let mut db = usvg::fontdb::Database::new();
+            db.load_system_fonts();
+            Arc::new(db)
+        })
+        .clone()
+}
+
+/// 期間内の日毎のPIXの推移を折れ線と塗りつぶしで描く; PIXを得なかった日は0とする
+fn sparkline(daily: &HashMap<NaiveDate, u32>, window: Window, stroke: &str) -> String {
+    let values = window
+        .from
+        .iter_days()
+        .take_while(|date| *date <= window.to)
+        .map(|date| daily.get(&date).copied().unwrap_or(0))
+        .collect::<Vec<_>>();
+    if values.len() < 2 {
+        return String::new();
+    }
+    let max = values.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let step = (CHART_RIGHT - CHART_LEFT) / (values.len() - 1) as f32;
+    let points = values
+        .iter()
+        .enumerate()
+        .map(|(i, amount)| {
+            format!(
+                "{:.1},{:.1}",
+                CHART_LEFT + step * i as f32,
+                CHART_BOTTOM - (CHART_BOTTOM - CHART_TOP) * *amount as f32 / max
+            )
+        })
+        .join(" ");
+    format!(
+        concat!(
+            r#"<polygon points="{left},{bottom} {points} {right},{bottom}" fill="{stroke}" fill-opacity=".15"/>"#,
+            r#"<polyline points="{points}" fill="none" stroke="{stroke}" stroke-width="4" stroke-linejoin="round" stroke-linecap="round"/>"#,
+        ),
+        left = CHART_LEFT,
+        right = CHART_RIGHT,
+        bottom = CHART_BOTTOM,
+        points = points,
+        stroke = stroke,
+    )
+}
+
+/// プレビュー画像のSVGを組み立てる
+fn svg(profile: &UserProfile, window: Window) -> String {
+    let pgn = &profile.pgn;
+    let (defs, fill) = match color(pgn.level) {
+        Some(color) => (String::new(), color.to_string()),
+        None => (rainbow("r"), "url(#r)".to_string()),
+    };
+    let next = match (pgn.target, pgn.behind_next) {
+        (Some(target), Some(behind)) => {
+            format!("{} PIX to go (next: {} PIX)", behind, target)
+        }
+        _ => "Top level reached".to_string(),
+    };
+    let progress_width = (CHART_RIGHT - CHART_LEFT) * pgn.progress.unwrap_or(1.0).clamp(0.0, 1.0);
+    format!(
+        concat!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"##,
+            r##"<defs>{defs}</defs>"##,
+            r##"<rect width="{width}" height="{height}" fill="#ffffff"/>"##,
+            r##"<rect width="{width}" height="16" fill="{fill}"/>"##,
+            r##"<g font-family="{font}" fill="#222222">"##,
+            r##"<text x="80" y="130" font-size="72" font-weight="bold">{name}</text>"##,
+            r##"<text x="80" y="230" font-size="88" font-weight="bold" fill="{fill}">{level}</text>"##,
+            r##"<text x="1120" y="230" font-size="56" text-anchor="end">{last_month} PIX</text>"##,
+            r##"<text x="1120" y="130" font-size="32" text-anchor="end" fill="#777777">last 30 days</text>"##,
+            r##"<text x="80" y="300" font-size="32" fill="#555555">{next}</text>"##,
+            r##"</g>"##,
+            r##"<rect x="{left}" y="330" width="{chart_width}" height="16" rx="8" fill="#eeeeee"/>"##,
+            r##"<rect x="{left}" y="330" width="{progress_width:.1}" height="16" rx="8" fill="{fill}"/>"##,
+            r##"{sparkline}"##,
+            r##"<text x="1120" y="610" font-family="{font}" font-size="24" text-anchor="end" fill="#999999">PGN</text>"##,
+            r##"</svg>"##,
+        ),
+        width = WIDTH,
+        height = HEIGHT,
+        defs = defs,
+        fill = fill,
+        font = FONT_FAMILY,
+        name = escape(&profile.user.pgrit_id),
+        level = pgn.level,
+        last_month = pgn.last_month,
+        next = escape(&next),
+        left = CHART_LEFT,
+        chart_width = CHART_RIGHT - CHART_LEFT,
+        progress_width = progress_width,
+        sparkline = sparkline(&pgn.daily, window, &fill),
+    )
+}
+
+/// プロフィールのプレビュー画像をPNGで描画する; `window`はプロフィールを計算した期間
+pub fn render(profile: &UserProfile, window: Window) -> Result<Vec<u8>, anyhow::Error> {
+    let options = usvg::Options {
+        fontdb: fontdb(),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_str(&svg(profile, window), &options)?;
+    let mut pixmap =
+        tiny_skia::Pixmap::new(WIDTH, HEIGHT).ok_or(anyhow::anyhow!("invalid image size"))?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    Ok(pixmap.encode_png()?)
+}
+
+/// 既存のOpenGraphのメタタグを取り除き、プロフィールに合わせたものを`</head>`の直前に挿入する
+pub fn inject_meta(html: &str, profile: &UserProfile, origin: &str) -> String {
+    let pgrit_id = &profile.user.pgrit_id;
+    let tags = [
+        (
+            "og:title",
+            format!("{} - {} | PGN", pgrit_id, profile.pgn.level),
+        ),
+        (
+            "og:description",
+            format!("{} PIX in the last 30 days", profile.pgn.last_month),
+        ),
+        ("og:url", format!("{}/profile/{}/", origin, pgrit_id)),
+        ("og:image", format!("{}/og/{}.png", origin, pgrit_id)),
+        ("og:image:width", WIDTH.to_string()),
+        ("og:image:height", HEIGHT.to_string()),
+    ];
+
+    let mut html = html.to_string();
+    for (property, _) in &tags {
+        let needle = format!(r#"<meta property="{}""#, property);
+        while let Some(start) = html.find(&needle) {
+            let Some(len) = html[start..].find('>') else {
+                break;
+            };
+            html.replace_range(start..=start + len, "");
+        }
+    }
+    let mut meta = tags
+        .iter()
+        .map(|(property, content)| {
+            format!(
+                r#"<meta property="{}" content="{}" />"#,
+                property,
+                escape(content)
+            )
+        })
+        .collect::<String>();
+    meta.push_str(r#"<meta name="twitter:card" content="summary_large_image" />"#);
+    match html.find("</head>") {
+        Some(head) => html.insert_str(head, &meta),
+        None => html.insert_str(0, &meta),
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use entity::{
+        pgn_level::PgnLevel,
+        user,
+        user_profile::{Forecast, PgnInfo},
+    };
+
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn profile(pgrit_id: &str) -> UserProfile {
+        UserProfile {
+            user: user::Model {
+                id: "0xa".to_string(),
+                pgrit_id: pgrit_id.to_string(),
+            },
+            student: None,
+            created_at: Utc::now(),
+            pgn: PgnInfo {
+                updated_at: Utc::now(),
+                daily: Default::default(),
+                level: PgnLevel::Bronze,
+                last_month: 600,
+                on_level: 100,
+                level_length: Some(500),
+                progress: Some(0.2),
+                target: Some(1000),
+                behind_next: Some(400),
+                sources: None,
+                season: None,
+                forecast: Forecast {
+                    expiring: 0,
+                    days: Vec::new(),
+                    required_daily: 0,
+                },
+            },
+            goals: Vec::new(),
+            achievements: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn sparkline_plots_missing_days_as_zero() {
+        let window = Window::at(date("2024-05-31"), date("2024-05-31")).unwrap();
+        // 5/2と5/4以降はPIXを得ていない
+        let daily = HashMap::from([(date("2024-05-01"), 10), (date("2024-05-03"), 10)]);
+        let svg = sparkline(&daily, window, "#000");
+
+        let points = svg
+            .split(r#"<polyline points=""#)
+            .nth(1)
+            .and_then(|s| s.split('"').next())
+            .unwrap()
+            .split(' ')
+            .collect::<Vec<_>>();
+        // 期間の30日分
+        assert_eq!(points.len(), 30);
+        assert_eq!(
+            &points[..4],
+            ["80.0,420.0", "115.9,570.0", "151.7,420.0", "187.6,570.0"]
+        );
+        assert_eq!(points[29], "1120.0,570.0");
+    }
+
+    #[test]
+    fn inject_meta_into_head() {
+        let html = concat!(
+            r#"<html><head><title>PGN</title>"#,
+            r#"<meta property="og:title" content="PGN"><meta property="og:site_name" content="PGN">"#,
+            r#"</head><body><meta property="og:image" content="x"></body></html>"#,
+        );
+        let html = inject_meta(html, &profile("<alice>"), "https://pgn.example");
+
+        let (head, body) = html.split_once("</head>").unwrap();
+        assert_eq!(body, "<body></body></html>");
+        // 既存のタグは置き換え、関係のないタグは残す
+        assert_eq!(head.matches(r#"property="og:title""#).count(), 1);
+        assert!(head.contains(r#"<meta property="og:site_name" content="PGN">"#));
+        assert!(
+            head.contains(r#"<meta property="og:title" content="&lt;alice&gt; - Bronze | PGN" />"#)
+        );
+        assert!(head.contains(
+            r#"<meta property="og:image" content="https://pgn.example/og/&lt;alice&gt;.png" />"#
+        ));
+        assert!(head.ends_with(r#"<meta name="twitter:card" content="summary_large_image" />"#));
+    }
+
+    #[test]
+    fn inject_meta_without_head() {
+        let html = inject_meta("<p>PGN</p>", &profile("alice"), "https://pgn.example");
+        assert!(html.starts_with(r#"<meta property="og:title""#));
+        assert!(html.ends_with("<p>PGN</p>"));
+    }
+}
@@ -0,0 +1,69 @@
+//! 定期的にリフレッシュを実行するスケジューラ
+
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use chrono::Local;
+use rand::Rng;
+use sea_orm::DatabaseConnection;
+
+/// リフレッシュの実行スケジュール
+pub enum Schedule {
+    /// 一定間隔で実行
+    Interval(Duration),
+    /// cron式に従って実行
+    Cron(Box<cron::Schedule>),
+}
+
+impl Schedule {
+    /// 設定値からスケジュールを生成する。cron式が指定されている場合はそちらを優先する
+    pub fn new(
+        interval_minutes: Option<u64>,
+        cron: Option<&str>,
+    ) -> Result<Option<Self>, cron::error::Error> {
+        if let Some(cron) = cron {
+            return Ok(Some(Schedule::Cron(Box::new(cron::Schedule::from_str(
+                cron,
+            )?))));
+        }
+        Ok(interval_minutes
+            .filter(|m| *m > 0)
+            .map(|m| Schedule::Interval(Duration::from_secs(m * 60))))
+    }
+
+    /// 次の実行までの待ち時間
+    fn next_wait(&self) -> Option<Duration> {
+        match self {
+            Schedule::Interval(interval) => Some(*interval),
+            Schedule::Cron(schedule) => {
+                let next = schedule.upcoming(Local).next()?;
+                (next - Local::now()).to_std().ok()
+            }
+        }
+    }
+}
+
+/// スケジュールに従ってリフレッシュを繰り返す
+pub async fn run(
+    db: DatabaseConnection,
+    fetch_url: Arc<str>,
+    notifiers: Arc<crate::Notifiers>,
+    schedule: Schedule,
+    jitter: Duration,
+) {
+    loop {
+        let Some(wait) = schedule.next_wait() else {
+            // 以降の実行予定がない
+            return;
+        };
+        let jitter = if jitter.is_zero() {
+            Duration::ZERO
+        } else {
+            rand::thread_rng().gen_range(Duration::ZERO..=jitter)
+        };
+        tokio::time::sleep(wait + jitter).await;
+
+        if let Err(e) = crate::refresh(&db, &fetch_url, &notifiers).await {
+            eprintln!("{}", e);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! 接続先のデータベースに応じてセッションの保存先を切り替える
+
+use async_trait::async_trait;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend};
+use tower_sessions::{
+    session::{Id, Record},
+    session_store, SessionStore,
+};
+use tower_sessions_sqlx_store::{sqlx, MySqlStore, PostgresStore, SqliteStore};
+
+/// データベースの種類ごとのセッションストア
+#[derive(Debug, Clone)]
+pub enum DbSessionStore {
+    Sqlite(SqliteStore),
+    Postgres(PostgresStore),
+    MySql(MySqlStore),
+}
+
+impl DbSessionStore {
+    /// 接続済みのデータベースのプールを使ってセッションストアを作成し、テーブルを作成する
+    pub async fn new(db: &DatabaseConnection) -> Result<Self, sqlx::Error> {
+        let store = match db.get_database_backend() {
+            DbBackend::Sqlite => {
+                let store = SqliteStore::new(db.get_sqlite_connection_pool().clone());
+                store.migrate().await?;
+                DbSessionStore::Sqlite(store)
+            }
+            DbBackend::Postgres => {
+                let store = PostgresStore::new(db.get_postgres_connection_pool().clone());
+                store.migrate().await?;
+                DbSessionStore::Postgres(store)
+            }
+            DbBackend::MySql => {
+                let store = MySqlStore::new(db.get_mysql_connection_pool().clone());
+                store.migrate().await?;
+                DbSessionStore::MySql(store)
+            }
+        };
+        Ok(store)
+    }
+}
+
+#[async_trait]
+impl SessionStore for DbSessionStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        match self {
+            DbSessionStore::Sqlite(store) => store.create(record).await,
+            DbSessionStore::Postgres(store) => store.create(record).await,
+            DbSessionStore::MySql(store) => store.create(record).await,
+        }
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        match self {
+            DbSessionStore::Sqlite(store) => store.save(record).await,
+            DbSessionStore::Postgres(store) => store.save(record).await,
+            DbSessionStore::MySql(store) => store.save(record).await,
+        }
+    }
+
+    async fn load(&self, id: &Id) -> session_store::Result<Option<Record>> {
+        match self {
+            DbSessionStore::Sqlite(store) => store.load(id).await,
+            DbSessionStore::Postgres(store) => store.load(id).await,
+            DbSessionStore::MySql(store) => store.load(id).await,
+        }
+    }
+
+    async fn delete(&self, id: &Id) -> session_store::Result<()> {
+        match self {
+            DbSessionStore::Sqlite(store) => store.delete(id).await,
+            DbSessionStore::Postgres(store) => store.delete(id).await,
+            DbSessionStore::MySql(store) => store.delete(id).await,
+        }
+    }
+}
@@ -1,31 +1,105 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use anyhow::Context;
 use axum::http::HeaderValue;
-use chrono::{Local, NaiveDate};
+use chrono::{Datelike, Local, NaiveDate, TimeZone};
 use entity::{
+    achievement, announcement,
+    cohort::{Cohort, CohortMonth, CohortReport},
+    compare::{ComparedUser, Comparison, HeadToHead},
+    degree::Degree,
     error::Error,
+    goal,
     grade::Grade,
-    mstdn_token,
-    pgn_level::{self, PgnLevel},
-    pix,
+    group_stats::{GroupBy, GroupStat, GroupStats, LevelCount},
+    import_report::{ImportReport, RecordReport},
+    leaderboard::{Leaderboard, LeaderboardEntry},
+    level::Level,
+    level_stats::{LevelStats, StudentLevelStats},
+    level_timeline::{LevelChange, LevelPoint, LevelTimeline},
+    mstdn_token, notification,
+    pgn_level::PgnLevel,
+    pix, pix_source, public_profile,
     record::Record,
-    refreshed_users, student, user,
-    user_profile::{PgnInfo, UserProfile},
+    refresh_job,
+    refresh_status::RefreshStatus,
+    refreshed_users, season, student, user, user_level,
+    user_profile::{Forecast, ForecastDay, GoalProgress, PgnInfo, PixSources, UserProfile},
 };
 use itertools::Itertools;
 use reqwest::{header, Url};
 use sea_orm::{
-    prelude::DateTimeUtc, sea_query::OnConflict, ActiveValue, ColumnTrait, DatabaseConnection,
-    EntityTrait, QueryFilter, QueryOrder, QuerySelect, RelationTrait, TransactionTrait,
+    prelude::DateTimeUtc,
+    sea_query::{Alias, Expr, Func, OnConflict, SimpleExpr},
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend,
+    EntityTrait, FromQueryResult, QueryFilter, QueryOrder, QuerySelect, RelationTrait,
+    TransactionTrait,
 };
 use serde_json::Value;
 use ulid::Ulid;
 use valq::query_value;
 
+use crate::achievement::{History, RULES};
+
 const CHUNK_SIZE: usize = 512;
 
-pub async fn fetch(url: &str, start: NaiveDate, end: NaiveDate) -> Result<Vec<Record>, Error> {
+/// PgnLevelを計算する期間の日数
+pub(crate) const LEVEL_WINDOW_DAYS: i64 = 30;
+
+/// 降格の予測を行う日数
+const FORECAST_DAYS: i64 = 7;
+
+/// PostgreSQLは符号なし整数を扱えないため、データベース上では符号付きで保存する
+pub(crate) fn to_db(value: u32) -> i32 {
+    value.min(i32::MAX as u32) as i32
+}
+
+/// データベース上の符号付き整数をPIXの値に戻す
+pub(crate) fn from_db(value: i32) -> u32 {
+    value.max(0) as u32
+}
+
+/// 取得に失敗した際の再試行回数
+const FETCH_RETRIES: u32 = 4;
+/// 再試行までの待ち時間の初期値; 失敗する毎に倍になる
+const FETCH_BACKOFF: std::time::Duration = std::time::Duration::from_secs(2);
+/// 1回の取得のタイムアウト
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+/// 取得したレコードを寛容に読み込む。
+/// 1件の不正な値のために全体が失敗しないよう、問題のあったフィールドやレコードは読み飛ばして警告として返す
+pub fn parse_records(values: Vec<Value>) -> (Vec<Record>, Vec<RecordReport>) {
+    let mut records = Vec::new();
+    let mut warnings = Vec::new();
+    for (index, value) in values.into_iter().enumerate() {
+        let id = query_value!(value.id -> str).map(str::to_string);
+        let wallet_address = query_value!(value.walletAddress -> str).map(str::to_string);
+        let (errors, skipped) = match Record::parse_lenient(value) {
+            Ok((record, errors)) => {
+                records.push(record);
+                (errors, false)
+            }
+            Err(errors) => (errors, true),
+        };
+        if !errors.is_empty() {
+            warnings.push(RecordReport {
+                index,
+                id,
+                wallet_address,
+                errors,
+                skipped,
+            });
+        }
+    }
+    (records, warnings)
+}
+
+/// 期間内のレコードを取得する。読み込めなかったフィールドやレコードは警告として返す
+pub async fn fetch(
+    url: &str,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<(Vec<Record>, Vec<RecordReport>), Error> {
     if start >= end {
         return Err(Error::InvalidDateRange);
     }
@@ -36,51 +110,138 @@ pub async fn fetch(url: &str, start: NaiveDate, end: NaiveDate) -> Result<Vec<Re
             ("end", &end.format("%Y-%m-%d").to_string()),
         ],
     )
-    .unwrap();
+    .with_context(|| format!("Invalid fetch URL: {}", url))?;
 
-    let response = reqwest::get(url).await?.text().await?;
-    let records = serde_json::from_str(&response)?;
-    Ok(records)
+    let response = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()?
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let values: Vec<Value> = serde_json::from_str(&response)?;
+    Ok(parse_records(values))
 }
 
+/// 通信エラーの場合は指数バックオフで再試行しながらfetchする
+pub async fn fetch_with_retry(
+    url: &str,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<(Vec<Record>, Vec<RecordReport>), Error> {
+    let mut backoff = FETCH_BACKOFF;
+    let mut retries = 0;
+    loop {
+        match fetch(url, start, end).await {
+            Err(Error::Reqwest(e)) if retries < FETCH_RETRIES => {
+                eprintln!("Fetch failed, retrying in {:?}: {}", backoff, e);
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                retries += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// PgnInfoを計算する30日間の期間[from, to]。
+/// PgnLevelの閾値は30日間のPIXに対するものなので、期間は常に30日間とする
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl Window {
+    /// toを最終日とする30日間。今日のデータは途中までしかないので含められない
+    pub fn ending(to: NaiveDate, today: NaiveDate) -> Result<Self, Error> {
+        if to >= today {
+            return Err(Error::InvalidDateRange);
+        }
+        Window::at(to.succ_opt().ok_or(Error::InvalidDateRange)?, today)
+    }
+
+    /// 指定日のPgnLevelを決める期間; 前日までの30日間。今日より後の日は指定できない
+    pub fn at(date: NaiveDate, today: NaiveDate) -> Result<Self, Error> {
+        if date > today {
+            return Err(Error::InvalidDateRange);
+        }
+        let from = date.checked_sub_signed(chrono::Duration::days(LEVEL_WINDOW_DAYS));
+        match (from, date.pred_opt()) {
+            (Some(from), Some(to)) => Ok(Window { from, to }),
+            _ => Err(Error::InvalidDateRange),
+        }
+    }
+}
+
+/// 現在のプロフィール; 今日のデータは含めない
 pub async fn profile(
     db: &DatabaseConnection,
     now: DateTimeUtc,
     pgrit_id: &str,
 ) -> Result<Option<UserProfile>, Error> {
-    let joined_table = pix::Entity::find()
-        .select_also(user::Entity)
-        .join(sea_orm::JoinType::InnerJoin, pix::Relation::User.def())
+    let today = now.with_timezone(&Local).date_naive();
+    profile_in(db, now, pgrit_id, Window::at(today, today)?).await
+}
+
+/// 指定した期間のPIXで計算したプロフィール
+pub async fn profile_in(
+    db: &DatabaseConnection,
+    now: DateTimeUtc,
+    pgrit_id: &str,
+    window: Window,
+) -> Result<Option<UserProfile>, Error> {
+    let Some(user) = user::Entity::find()
         .filter(user::Column::PgritId.eq(pgrit_id))
-        .filter(pix::Column::Date.lt(now.with_timezone(&Local).date_naive())) // 今日のデータは含めない
-        .order_by(pix::Column::Date, sea_orm::Order::Desc)
-        .limit(30)
-        .all(db)
-        .await
-        .unwrap();
-
-    // 全て同じUser
-    let user: user::Model = {
-        let Some(m) = joined_table.first() else {
-            // 指定されたPgrit IDのユーザが存在しない
-            return Ok(None);
-        };
-        m.1.clone().unwrap()
-    };
-    let student: Option<student::Model> = student::Entity::find_by_id(&user.id.clone())
         .one(db)
+        .await?
+    else {
+        // 指定されたPgrit IDのユーザが存在しない
+        return Ok(None);
+    };
+    let pixes = pix::Entity::find()
+        .filter(pix::Column::UserId.eq(&user.id))
+        .filter(pix::Column::Date.gte(window.from))
+        .filter(pix::Column::Date.lte(window.to))
+        .all(db)
         .await?;
 
+    let student: Option<student::Model> =
+        student::Entity::find_by_id(user.id.clone()).one(db).await?;
+    let sources: Option<PixSources> = pix_source::Entity::find()
+        .filter(pix_source::Column::UserId.eq(&user.id))
+        .order_by_desc(pix_source::Column::Ulid)
+        .one(db)
+        .await?
+        .map(|m| PixSources {
+            start_date: m.start_date,
+            end_date: m.end_date,
+            pgrit: from_db(m.pgrit),
+            dawn: from_db(m.dawn),
+            other: from_db(m.other),
+        });
+
+    // 期間の最終日に有効なシーズンの閾値を用いる。予測や目標は期間の翌日を基準にする
+    let today = window.to + chrono::Duration::days(1);
+    let seasons = seasons(db).await?;
+    let season = season::in_force(&seasons, window.to).cloned();
+    let thresholds = season
+        .as_ref()
+        .map(season::Model::thresholds)
+        .unwrap_or_default();
+
     let pgn: PgnInfo = {
-        let daily: HashMap<NaiveDate, u32> = joined_table
+        let daily: HashMap<NaiveDate, u32> = pixes
             .into_iter()
-            .map(|(pix, _)| (pix.date, pix.amount))
+            .map(|pix| (pix.date, from_db(pix.amount)))
             .collect();
 
         let last_month: u32 = daily.values().sum();
-        let level = pgn_level::PgnLevel::from(last_month);
+        let level = thresholds.level(last_month);
 
-        let base_pix = level.min_pix();
+        let base_pix = thresholds.min_pix(level);
         let on_level = last_month - base_pix;
 
         let mut level_length = None;
@@ -88,7 +249,7 @@ pub async fn profile(
         let mut target = None;
         let mut behind_next = None;
         if level != PgnLevel::GrandMaster {
-            let t = (level + 1).min_pix();
+            let t = thresholds.min_pix(level + 1);
             let ll = t - base_pix;
             target = Some(t);
             level_length = Some(ll);
@@ -101,53 +262,1244 @@ pub async fn profile(
                 Some(on_level as f32 / ll as f32)
             };
 
-            behind_next = Some(t - last_month);
-        };
-        PgnInfo {
-            level,
-            level_length,
-            last_month,
-            on_level,
-            progress,
-            target,
-            behind_next,
-            daily,
-            updated_at: get_last_updated_at(db).await?.unwrap(),
+            behind_next = Some(t - last_month);
+        };
+        PgnInfo {
+            level,
+            level_length,
+            last_month,
+            on_level,
+            progress,
+            target,
+            behind_next,
+            forecast: forecast(&daily, &seasons, today, level),
+            daily,
+            sources,
+            season,
+            updated_at: get_last_updated_at(db).await?.unwrap_or(now),
+        }
+    };
+
+    let goals = goal_progress(db, &user.id, today, pgn.last_month, &seasons).await?;
+    let achievements = achievements(db, &user.id).await?;
+
+    Ok(Some(UserProfile {
+        user,
+        student,
+        created_at: now,
+        pgn,
+        goals,
+        achievements,
+    }))
+}
+
+/// 複数のユーザの現在のPgnLevelと日毎のPIXを並べて比較する。存在しないユーザが含まれる場合はNone
+pub async fn compare(
+    db: &DatabaseConnection,
+    now: DateTimeUtc,
+    pgrit_ids: &[String],
+) -> Result<Option<Comparison>, Error> {
+    let found = user::Entity::find()
+        .filter(user::Column::PgritId.is_in(pgrit_ids.iter().cloned()))
+        .all(db)
+        .await?;
+    // 指定された順に並べる
+    let Some(users) = pgrit_ids
+        .iter()
+        .map(|id| found.iter().find(|u| &u.pgrit_id == id).cloned())
+        .collect::<Option<Vec<_>>>()
+    else {
+        return Ok(None);
+    };
+    if users.is_empty() {
+        return Ok(None);
+    }
+
+    // プロフィールと同じく今日のPgnLevelの期間で比べる
+    let today = now.with_timezone(&Local).date_naive();
+    let window = Window::at(today, today)?;
+    let mut daily: HashMap<String, HashMap<NaiveDate, u32>> = HashMap::new();
+    for pix in pix::Entity::find()
+        .filter(pix::Column::UserId.is_in(users.iter().map(|u| u.id.clone())))
+        .filter(pix::Column::Date.gte(window.from))
+        .filter(pix::Column::Date.lte(window.to))
+        .all(db)
+        .await?
+    {
+        daily
+            .entry(pix.user_id)
+            .or_default()
+            .insert(pix.date, from_db(pix.amount));
+    }
+    let thresholds = season::thresholds_at(&seasons(db).await?, window.to);
+    let updated_at = get_last_updated_at(db).await?.unwrap_or(now);
+
+    let dates = daily
+        .values()
+        .flat_map(|d| d.keys().copied())
+        .sorted()
+        .dedup()
+        .collect_vec();
+    let mut users = users
+        .into_iter()
+        .map(|user| {
+            let daily = daily.remove(&user.id).unwrap_or_default();
+            let last_month = daily.values().sum();
+            ComparedUser {
+                daily: dates
+                    .iter()
+                    .map(|d| daily.get(d).copied().unwrap_or(0))
+                    .collect(),
+                last_month,
+                level: thresholds.level(last_month),
+                rank_in_comparison: 0,
+                user,
+            }
+        })
+        .collect_vec();
+    for i in 0..users.len() {
+        let last_month = users[i].last_month;
+        users[i].rank_in_comparison =
+            users.iter().filter(|u| u.last_month > last_month).count() as u64 + 1;
+    }
+
+    let head_to_head = users
+        .iter()
+        .tuple_combinations()
+        .map(|(a, b)| {
+            let days = a.daily.iter().zip(&b.daily);
+            HeadToHead {
+                a: a.user.pgrit_id.clone(),
+                b: b.user.pgrit_id.clone(),
+                pix_diff: a.last_month as i64 - b.last_month as i64,
+                level_diff: a.level as i32 - b.level as i32,
+                days_ahead: days.clone().filter(|(a, b)| a > b).count() as u32,
+                days_behind: days.filter(|(a, b)| a < b).count() as u32,
+            }
+        })
+        .collect();
+
+    Ok(Some(Comparison {
+        updated_at,
+        dates,
+        users,
+        head_to_head,
+    }))
+}
+
+/// ユーザの目標それぞれについて、PIXの履歴から進捗を求める
+async fn goal_progress(
+    db: &DatabaseConnection,
+    user_id: &str,
+    today: NaiveDate,
+    last_month: u32,
+    seasons: &[season::Model],
+) -> Result<Vec<GoalProgress>, Error> {
+    let goals = goals(db, user_id).await?;
+    let Some(from) = goals
+        .iter()
+        .map(|g| {
+            g.created_at
+                .with_timezone(&Local)
+                .date_naive()
+                .min(g.deadline)
+        })
+        .min()
+    else {
+        return Ok(Vec::new());
+    };
+    let daily: BTreeMap<NaiveDate, u32> = pix::Entity::find()
+        .filter(pix::Column::UserId.eq(user_id))
+        .filter(pix::Column::Date.gte(from - chrono::Duration::days(LEVEL_WINDOW_DAYS)))
+        .filter(pix::Column::Date.lt(today)) // 今日のデータは含めない
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|pix| (pix.date, from_db(pix.amount)))
+        .collect();
+    let progress = goals
+        .into_iter()
+        .map(|goal| {
+            // 期限日のPgnLevelは前日までの30日間で決まる
+            let last_day = goal.deadline - chrono::Duration::days(1);
+            let target_level = goal
+                .target_level
+                .as_deref()
+                .and_then(|l| l.parse::<PgnLevel>().ok());
+            let target_pix = match target_level {
+                Some(level) => season::thresholds_at(seasons, last_day).min_pix(level),
+                None => from_db(goal.target_pix.unwrap_or_default()),
+            };
+            let remaining = target_pix.saturating_sub(last_month);
+
+            let days_left = (goal.deadline - today).num_days().max(0);
+            let required_daily = (days_left > 0).then(|| {
+                // 期限日の集計期間のうち、既に得たPIXを除いた分を残りの日数で得る
+                let kept: u32 = daily
+                    .range(goal.deadline - chrono::Duration::days(LEVEL_WINDOW_DAYS)..)
+                    .map(|(_, amount)| amount)
+                    .sum();
+                let days = days_left.min(LEVEL_WINDOW_DAYS) as u32;
+                target_pix.saturating_sub(kept).div_ceil(days)
+            });
+
+            let created = goal.created_at.with_timezone(&Local).date_naive();
+            let achieved_on = rolling_levels(&daily, seasons, created, goal.deadline.min(today))
+                .into_iter()
+                .find(|p| match target_level {
+                    Some(level) => p.level >= level,
+                    None => p.window_pix >= target_pix,
+                })
+                .map(|p| p.date);
+
+            GoalProgress {
+                target_pix,
+                current_pix: last_month,
+                progress: match target_pix {
+                    0 => 1.0,
+                    t => (last_month as f32 / t as f32).min(1.0),
+                },
+                remaining,
+                days_left,
+                required_daily,
+                achieved_on,
+                goal,
+            }
+        })
+        .collect();
+    Ok(progress)
+}
+
+/// 今後PIXを得なかった場合に、集計期間から外れていくPIXとPgnLevelを予測する
+fn forecast(
+    daily: &HashMap<NaiveDate, u32>,
+    seasons: &[season::Model],
+    today: NaiveDate,
+    level: PgnLevel,
+) -> Forecast {
+    // 今日の集計期間は[today - 30, today)
+    let mut window_pix: u32 = daily
+        .iter()
+        .filter(|(date, _)| {
+            (today - chrono::Duration::days(LEVEL_WINDOW_DAYS)..today).contains(*date)
+        })
+        .map(|(_, amount)| amount)
+        .sum();
+    let mut expiring = 0;
+    let mut required_daily = 0;
+    let mut days = Vec::new();
+    for k in 1..=FORECAST_DAYS {
+        let date = today + chrono::Duration::days(k);
+        // dateの集計期間は[date - 30, date)なので、その前日が外れる
+        let leaving = daily
+            .get(&(date - chrono::Duration::days(LEVEL_WINDOW_DAYS + 1)))
+            .copied()
+            .unwrap_or(0);
+        window_pix -= leaving.min(window_pix);
+        expiring += leaving;
+
+        let thresholds = season::thresholds_at(seasons, date - chrono::Duration::days(1));
+        // k日間毎日同じだけ得た場合に現在のレベルを維持できる量
+        let shortage = thresholds.min_pix(level).saturating_sub(window_pix);
+        required_daily = required_daily.max(shortage.div_ceil(k as u32));
+
+        days.push(ForecastDay {
+            date,
+            expiring: leaving,
+            window_pix,
+            level: thresholds.level(window_pix),
+        });
+    }
+    Forecast {
+        expiring,
+        days,
+        required_daily,
+    }
+}
+
+/// 登録されているシーズンをeffective_fromの昇順で取得する
+pub async fn seasons(db: &DatabaseConnection) -> Result<Vec<season::Model>, Error> {
+    let seasons = season::Entity::find()
+        .order_by_asc(season::Column::EffectiveFrom)
+        .all(db)
+        .await?;
+    Ok(seasons)
+}
+
+/// 日毎のPIXから、期間[from, to]の各日のPgnLevelを求める。
+/// ある日のPgnLevelは`Window::at`と同じく前日までの30日間のPIXで決まり、前日に有効なシーズンの閾値を用いる
+pub fn rolling_levels(
+    daily: &BTreeMap<NaiveDate, u32>,
+    seasons: &[season::Model],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<LevelPoint> {
+    let mut points = Vec::new();
+    let mut window_pix: u32 = daily
+        .range(from - chrono::Duration::days(LEVEL_WINDOW_DAYS)..from)
+        .map(|(_, amount)| amount)
+        .sum();
+    for date in from.iter_days().take_while(|d| *d <= to) {
+        points.push(LevelPoint {
+            date,
+            window_pix,
+            level: season::thresholds_at(seasons, date - chrono::Duration::days(1))
+                .level(window_pix),
+        });
+        // 翌日にはその日のPIXが加わり、30日前のPIXが期間から外れる
+        window_pix += daily.get(&date).copied().unwrap_or(0);
+        window_pix -= daily
+            .get(&(date - chrono::Duration::days(LEVEL_WINDOW_DAYS)))
+            .copied()
+            .unwrap_or(0);
+    }
+    points
+}
+
+/// PgnLevel毎の人数をIronから昇順に数える
+fn level_counts(levels: impl IntoIterator<Item = PgnLevel>) -> Vec<LevelCount> {
+    let mut counts = vec![0; PgnLevel::GrandMaster as usize + 1];
+    for level in levels {
+        counts[level as usize] += 1;
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(step, members)| LevelCount {
+            level: PgnLevel::Iron + step,
+            members,
+        })
+        .collect()
+}
+
+#[derive(FromQueryResult)]
+struct GroupCount {
+    group_name: String,
+    members: i64,
+}
+
+#[derive(FromQueryResult)]
+struct GroupPixSum {
+    group_name: String,
+    total: Option<i64>,
+}
+
+/// アクティブな学生を学生情報の項目でグループ分けし、直近30日のPIXを集計する
+pub async fn group_stats(
+    db: &DatabaseConnection,
+    now: DateTimeUtc,
+    by: GroupBy,
+) -> Result<Option<GroupStats>, Error> {
+    let Some(updated_at) = get_last_updated_at(db).await? else {
+        return Ok(None);
+    };
+    let column = match by {
+        GroupBy::Office => student::Column::Office,
+        GroupBy::University => student::Column::University,
+        GroupBy::Course => student::Column::Course,
+        GroupBy::Major => student::Column::Major,
+        GroupBy::Level => student::Column::Level,
+        GroupBy::DegreeStep => student::Column::DegreeStep,
+    };
+    // 今日のデータは含めない
+    let today = now.with_timezone(&Local).date_naive();
+    let start_date = today - chrono::Duration::days(LEVEL_WINDOW_DAYS);
+
+    let counts = student::Entity::find()
+        .select_only()
+        .column_as(column, "group_name")
+        .column_as(student::Column::UserId.count(), "members")
+        .filter(student::Column::Active.eq(true))
+        .group_by(column)
+        .into_model::<GroupCount>()
+        .all(db)
+        .await?;
+    // グループ毎にメンバーそれぞれの合計を求める
+    let mut sums: HashMap<String, Vec<u32>> = HashMap::new();
+    for sum in pix::Entity::find()
+        .select_only()
+        .column_as(column, "group_name")
+        .column(pix::Column::UserId)
+        .column_as(amount_sum(db), "total")
+        .join(
+            sea_orm::JoinType::InnerJoin,
+            pix::Entity::belongs_to(student::Entity)
+                .from(pix::Column::UserId)
+                .to(student::Column::UserId)
+                .into(),
+        )
+        .filter(student::Column::Active.eq(true))
+        .filter(pix::Column::Date.gte(start_date))
+        .filter(pix::Column::Date.lt(today))
+        .group_by(column)
+        .group_by(pix::Column::UserId)
+        .into_model::<GroupPixSum>()
+        .all(db)
+        .await?
+    {
+        sums.entry(sum.group_name)
+            .or_default()
+            .push(sum.total.unwrap_or(0).clamp(0, u32::MAX as i64) as u32);
+    }
+    let thresholds = season::thresholds_at(&seasons(db).await?, today - chrono::Duration::days(1));
+
+    let groups = counts
+        .into_iter()
+        .map(|count| {
+            let members = count.members.max(0) as u64;
+            // PIXを得ていないメンバーは0として扱う
+            let mut pix = sums.remove(&count.group_name).unwrap_or_default();
+            pix.resize(members.max(pix.len() as u64) as usize, 0);
+            pix.sort_unstable();
+            let median_pix = match pix.len() {
+                0 => 0.0,
+                n if n % 2 == 0 => (pix[n / 2 - 1] as f64 + pix[n / 2] as f64) / 2.0,
+                n => pix[n / 2] as f64,
+            };
+            let levels = level_counts(pix.iter().map(|p| thresholds.level(*p)));
+            GroupStat {
+                group: count.group_name,
+                members,
+                total_pix: pix.iter().map(|p| *p as u64).sum(),
+                median_pix,
+                levels,
+            }
+        })
+        .sorted_by(|a, b| {
+            b.members
+                .cmp(&a.members)
+                .then_with(|| a.group.cmp(&b.group))
+        })
+        .collect();
+
+    Ok(Some(GroupStats {
+        updated_at,
+        by,
+        start_date,
+        end_date: today - chrono::Duration::days(1),
+        groups,
+    }))
+}
+
+/// 指定日にアクティブだったユーザのPgnLevelの分布を、その日の集計期間のPIXから求める。
+/// アクティブなユーザはその日までで最後のリフレッシュで取得したユーザとする。
+/// 集計期間が表現できる日付の範囲外にはみ出す場合は`Error::InvalidDateRange`
+pub async fn level_stats(
+    db: &DatabaseConnection,
+    date: NaiveDate,
+) -> Result<Option<LevelStats>, Error> {
+    // その日のPgnLevelは前日までの30日間で決まる
+    let (Some(start_date), Some(prev_date), Some(next_date)) = (
+        date.checked_sub_signed(chrono::Duration::days(LEVEL_WINDOW_DAYS)),
+        date.pred_opt(),
+        date.succ_opt(),
+    ) else {
+        return Err(Error::InvalidDateRange);
+    };
+    let Some(updated_at) = get_last_updated_at(db).await? else {
+        return Ok(None);
+    };
+    // その日の終わりまでのリフレッシュがない場合は最初のリフレッシュを用いる
+    let until = Local
+        .from_local_datetime(&next_date.and_time(Default::default()))
+        .earliest()
+        .map(|d| Ulid::from_datetime(d.into()).to_string())
+        .unwrap_or_default();
+    let refresh_log_item = match refreshed_users::Entity::find()
+        .filter(refreshed_users::Column::Ulid.lt(until))
+        .order_by_desc(refreshed_users::Column::Ulid)
+        .one(db)
+        .await?
+    {
+        Some(item) => Some(item),
+        None => {
+            refreshed_users::Entity::find()
+                .order_by_asc(refreshed_users::Column::Ulid)
+                .one(db)
+                .await?
+        }
+    };
+    let Some(refresh_log_item) = refresh_log_item else {
+        return Ok(None);
+    };
+    let users = user::Entity::find()
+        .find_also_related(student::Entity)
+        .join(
+            sea_orm::JoinType::InnerJoin,
+            refreshed_users::Relation::User.def().rev(),
+        )
+        .filter(refreshed_users::Column::Ulid.eq(refresh_log_item.ulid))
+        .all(db)
+        .await?;
+
+    let sums = pix_sums(db, start_date, date).await?;
+    let thresholds = season::thresholds_at(&seasons(db).await?, prev_date);
+    let levels = users
+        .into_iter()
+        .map(|(user, student)| {
+            let level = thresholds.level(sums.get(&user.id).copied().unwrap_or(0));
+            (student.map(|s| s.level), level)
+        })
+        .collect_vec();
+
+    let student_levels = [
+        Some(Level::Newbie),
+        Some(Level::Assistant),
+        Some(Level::Normal),
+        Some(Level::Lead),
+        None,
+    ];
+    let by_student_level = student_levels
+        .into_iter()
+        .filter_map(|student_level| {
+            let group = levels
+                .iter()
+                .filter(|(l, _)| *l == student_level)
+                .map(|(_, level)| *level)
+                .collect_vec();
+            (!group.is_empty()).then(|| StudentLevelStats {
+                student_level,
+                members: group.len() as u64,
+                levels: level_counts(group),
+            })
+        })
+        .collect();
+
+    Ok(Some(LevelStats {
+        updated_at,
+        date,
+        start_date,
+        end_date: date - chrono::Duration::days(1),
+        members: levels.len() as u64,
+        levels: level_counts(levels.into_iter().map(|(_, level)| level)),
+        by_student_level,
+    }))
+}
+
+/// 月を年*12+(月-1)の通し番号で表す
+fn month_index(date: NaiveDate) -> i32 {
+    date.year() * 12 + date.month0() as i32
+}
+
+/// 月の通し番号をYYYY-MMの形式にする
+fn format_month(index: i32) -> String {
+    format!(
+        "{:04}-{:02}",
+        index.div_euclid(12),
+        index.rem_euclid(12) + 1
+    )
+}
+
+/// 学生の加入月; 取得元の加入月を解釈できない場合は加入日の月
+fn join_month_index(student: &student::Model) -> i32 {
+    student
+        .join_month
+        .as_deref()
+        .and_then(|m| {
+            // YYYY/MMとYYYY-MMのどちらの表記も受け付ける
+            let m = m.trim().replace('/', "-");
+            NaiveDate::parse_from_str(&format!("{}-01", m), "%Y-%m-%d").ok()
+        })
+        .map(month_index)
+        .unwrap_or_else(|| month_index(student.join_date))
+}
+
+#[derive(FromQueryResult)]
+struct MonthlyPixSum {
+    user_id: String,
+    month: String,
+    total: Option<i64>,
+}
+
+/// PIXの日付の年月をYYYY-MMの文字列にする式
+fn pix_month(db: &DatabaseConnection) -> SimpleExpr {
+    let date = Expr::col((pix::Entity, pix::Column::Date));
+    match db.get_database_backend() {
+        DbBackend::MySql => Func::cust(Alias::new("DATE_FORMAT"))
+            .arg(date)
+            .arg("%Y-%m")
+            .into(),
+        DbBackend::Postgres => Func::cust(Alias::new("TO_CHAR"))
+            .arg(date)
+            .arg("YYYY-MM")
+            .into(),
+        DbBackend::Sqlite => Func::cust(Alias::new("STRFTIME"))
+            .arg("%Y-%m")
+            .arg(date)
+            .into(),
+    }
+}
+
+/// 学生を加入月毎にまとめ、在籍率と加入からの経過月毎の1人あたりのPIXを求める
+pub async fn cohorts(
+    db: &DatabaseConnection,
+    now: DateTimeUtc,
+) -> Result<Option<CohortReport>, Error> {
+    let Some(updated_at) = get_last_updated_at(db).await? else {
+        return Ok(None);
+    };
+    // 今日のデータは含めない
+    let today = now.with_timezone(&Local).date_naive();
+    let current = month_index(today - chrono::Duration::days(1));
+
+    struct Members {
+        members: u64,
+        retained: u64,
+        totals: BTreeMap<i32, u64>,
+    }
+    let mut cohorts: BTreeMap<i32, Members> = BTreeMap::new();
+    let mut joined: HashMap<String, i32> = HashMap::new();
+    for student in student::Entity::find().all(db).await? {
+        let join = join_month_index(&student);
+        let cohort = cohorts.entry(join).or_insert(Members {
+            members: 0,
+            retained: 0,
+            totals: BTreeMap::new(),
+        });
+        cohort.members += 1;
+        if student.active && student.leave_date.is_none() {
+            cohort.retained += 1;
+        }
+        joined.insert(student.user_id, join);
+    }
+
+    // 加入月は取得元の表記を解釈して求めるので、SQLではユーザと月毎の合計までを求める
+    for sum in pix::Entity::find()
+        .select_only()
+        .column(pix::Column::UserId)
+        .column_as(pix_month(db), "month")
+        .column_as(amount_sum(db), "total")
+        .join(
+            sea_orm::JoinType::InnerJoin,
+            pix::Entity::belongs_to(student::Entity)
+                .from(pix::Column::UserId)
+                .to(student::Column::UserId)
+                .into(),
+        )
+        .filter(pix::Column::Date.lt(today))
+        .group_by(pix::Column::UserId)
+        // 式を繰り返すとPostgreSQLではパラメータが別になり同じ式とみなされないので別名で指定する
+        .group_by(Expr::col(Alias::new("month")))
+        .into_model::<MonthlyPixSum>()
+        .all(db)
+        .await?
+    {
+        let Some(join) = joined.get(&sum.user_id) else {
+            continue;
+        };
+        let Ok(date) = NaiveDate::parse_from_str(&format!("{}-01", sum.month), "%Y-%m-%d") else {
+            continue;
+        };
+        let month = month_index(date);
+        // 加入前のPIXは含めない
+        if month < *join {
+            continue;
+        }
+        if let Some(cohort) = cohorts.get_mut(join) {
+            *cohort.totals.entry(month).or_default() += sum.total.unwrap_or(0).max(0) as u64;
+        }
+    }
+
+    let cohorts = cohorts
+        .into_iter()
+        .map(|(join, cohort)| Cohort {
+            join_month: format_month(join),
+            members: cohort.members,
+            retained: cohort.retained,
+            retention: cohort.retained as f64 / cohort.members as f64,
+            months: (join..=current)
+                .map(|month| {
+                    let total_pix = cohort.totals.get(&month).copied().unwrap_or(0);
+                    CohortMonth {
+                        offset: (month - join) as u32,
+                        month: format_month(month),
+                        total_pix,
+                        average_pix: total_pix as f64 / cohort.members as f64,
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+
+    Ok(Some(CohortReport {
+        updated_at,
+        cohorts,
+    }))
+}
+
+/// ユーザのPgnLevelの推移を、保存されている全てのPIXの履歴から求める
+pub async fn level_timeline(
+    db: &DatabaseConnection,
+    now: DateTimeUtc,
+    pgrit_id: &str,
+) -> Result<Option<LevelTimeline>, Error> {
+    let Some(user) = user::Entity::find()
+        .filter(user::Column::PgritId.eq(pgrit_id))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let today = now.with_timezone(&Local).date_naive();
+    let daily: BTreeMap<NaiveDate, u32> = pix::Entity::find()
+        .filter(pix::Column::UserId.eq(&user.id))
+        .filter(pix::Column::Date.lt(today)) // 今日のデータは含めない
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|pix| (pix.date, from_db(pix.amount)))
+        .collect();
+
+    let seasons = seasons(db).await?;
+    let days = match daily.keys().next() {
+        Some(first) => rolling_levels(&daily, &seasons, *first + chrono::Duration::days(1), today),
+        None => Vec::new(),
+    };
+    let changes = days
+        .iter()
+        .tuple_windows()
+        .filter(|(prev, next)| prev.level != next.level)
+        .map(|(prev, next)| LevelChange {
+            date: next.date,
+            from: prev.level,
+            to: next.level,
+        })
+        .collect();
+
+    Ok(Some(LevelTimeline {
+        user,
+        updated_at: get_last_updated_at(db).await?.unwrap_or(now),
+        days,
+        changes,
+    }))
+}
+
+#[derive(FromQueryResult)]
+struct PixSum {
+    user_id: String,
+    total: Option<i64>,
+}
+
+/// PIXの合計を求める式
+fn amount_sum(db: &DatabaseConnection) -> SimpleExpr {
+    // MySQLのSUMはDECIMALを返すので整数に変換する
+    match db.get_database_backend() {
+        DbBackend::MySql => Func::cast_as(pix::Column::Amount.sum(), Alias::new("SIGNED")).into(),
+        _ => pix::Column::Amount.sum(),
+    }
+}
+
+/// 期間[from, to)におけるユーザ毎のPIXの合計を取得する
+pub async fn pix_sums(
+    db: &DatabaseConnection,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<HashMap<String, u32>, Error> {
+    let total = amount_sum(db);
+    let sums = pix::Entity::find()
+        .select_only()
+        .column(pix::Column::UserId)
+        .column_as(total, "total")
+        .filter(pix::Column::Date.gte(from))
+        .filter(pix::Column::Date.lt(to))
+        .group_by(pix::Column::UserId)
+        .into_model::<PixSum>()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|s| {
+            (
+                s.user_id,
+                s.total.unwrap_or(0).clamp(0, u32::MAX as i64) as u32,
+            )
+        })
+        .collect();
+    Ok(sums)
+}
+
+/// ランキングの絞り込み条件
+#[derive(Debug, Default)]
+pub struct LeaderboardFilter {
+    pub office: Option<String>,
+    pub university: Option<String>,
+    pub course: Option<String>,
+    pub level: Option<Level>,
+    pub degree_step: Option<Degree>,
+}
+
+/// アクティブなユーザを直近30日のPIXで順位付けする
+pub async fn leaderboard(
+    db: &DatabaseConnection,
+    now: DateTimeUtc,
+    filter: &LeaderboardFilter,
+    page: u64,
+    per_page: u64,
+) -> Result<Option<Leaderboard>, Error> {
+    let Some(updated_at) = get_last_updated_at(db).await? else {
+        return Ok(None);
+    };
+    let Some(refresh_log_item) = refreshed_users::Entity::find()
+        .column(refreshed_users::Column::Ulid)
+        .order_by_desc(refreshed_users::Column::Ulid)
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let mut query = user::Entity::find()
+        .find_also_related(student::Entity)
+        .join(
+            sea_orm::JoinType::InnerJoin,
+            refreshed_users::Relation::User.def().rev(),
+        )
+        .filter(refreshed_users::Column::Ulid.eq(refresh_log_item.ulid));
+    if let Some(office) = &filter.office {
+        query = query.filter(student::Column::Office.eq(office));
+    }
+    if let Some(university) = &filter.university {
+        query = query.filter(student::Column::University.eq(university));
+    }
+    if let Some(course) = &filter.course {
+        query = query.filter(student::Column::Course.eq(course));
+    }
+    if let Some(level) = &filter.level {
+        query = query.filter(student::Column::Level.eq(level.clone()));
+    }
+    if let Some(degree_step) = &filter.degree_step {
+        query = query.filter(student::Column::DegreeStep.eq(degree_step.clone()));
+    }
+    let users = query.all(db).await?;
+
+    // 今日のデータは含めない
+    let today = now.with_timezone(&Local).date_naive();
+    let sums = pix_sums(db, today - chrono::Duration::days(LEVEL_WINDOW_DAYS), today).await?;
+    let thresholds = season::thresholds_at(&seasons(db).await?, today - chrono::Duration::days(1));
+
+    let ranked = users
+        .into_iter()
+        .map(|(user, student)| {
+            let last_month = sums.get(&user.id).copied().unwrap_or(0);
+            (user, student, last_month)
+        })
+        .sorted_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.pgrit_id.cmp(&b.0.pgrit_id)))
+        .collect_vec();
+
+    let total = ranked.len() as u64;
+    let mut rank = 0;
+    let mut prev = None;
+    let entries = ranked
+        .into_iter()
+        .enumerate()
+        .map(|(i, (user, student, last_month))| {
+            if prev != Some(last_month) {
+                rank = i as u64 + 1;
+                prev = Some(last_month);
+            }
+            LeaderboardEntry {
+                rank,
+                user,
+                student,
+                last_month,
+                level: thresholds.level(last_month),
+            }
+        })
+        .skip(
+            page.saturating_sub(1)
+                .saturating_mul(per_page)
+                .try_into()
+                .unwrap_or(usize::MAX),
+        )
+        .take(per_page.try_into().unwrap_or(usize::MAX))
+        .collect();
+
+    Ok(Some(Leaderboard {
+        updated_at,
+        total,
+        page,
+        per_page,
+        entries,
+    }))
+}
+
+pub async fn active_users(db: &DatabaseConnection) -> Result<Option<Vec<user::Model>>, Error> {
+    let Some(refresh_log_item) = refreshed_users::Entity::find()
+        .column(refreshed_users::Column::Ulid)
+        .order_by_desc(refreshed_users::Column::Ulid)
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let log_id = refresh_log_item.ulid;
+
+    let users = user::Entity::find()
+        .join(
+            sea_orm::JoinType::LeftJoin,
+            refreshed_users::Relation::User.def().rev(),
+        )
+        .filter(refreshed_users::Column::Ulid.eq(log_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .collect_vec();
+
+    Ok(Some(users))
+}
+
+/// 前回確認した時からPgnLevelが変化したユーザ
+#[derive(Debug, Clone)]
+pub struct LevelUpdate {
+    pub user: user::Model,
+    /// 前回のPgnLevel
+    pub from: PgnLevel,
+    /// 現在のPgnLevel
+    pub to: PgnLevel,
+    /// 最近1ヶ月のPIX
+    pub last_month: u32,
+}
+
+/// 最新のリフレッシュに含まれるユーザのPgnLevelを記録し、前回から変化したユーザを返す。
+/// 初めて記録するユーザは変化として扱わない
+pub async fn update_levels(
+    db: &DatabaseConnection,
+    now: DateTimeUtc,
+) -> Result<Vec<LevelUpdate>, Error> {
+    let Some(users) = active_users(db).await? else {
+        return Ok(Vec::new());
+    };
+
+    // 今日のデータは含めない
+    let today = now.with_timezone(&Local).date_naive();
+    let sums = pix_sums(db, today - chrono::Duration::days(LEVEL_WINDOW_DAYS), today).await?;
+    let thresholds = season::thresholds_at(&seasons(db).await?, today - chrono::Duration::days(1));
+    let known: HashMap<String, user_level::Model> = user_level::Entity::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|l| (l.user_id.clone(), l))
+        .collect();
+
+    let mut updates = Vec::new();
+    let mut levels = Vec::new();
+    for user in users {
+        let last_month = sums.get(&user.id).copied().unwrap_or(0);
+        let level = thresholds.level(last_month);
+        let prev = known.get(&user.id);
+        let changed_at = match prev.map(|p| (p.level.parse::<PgnLevel>(), p.changed_at)) {
+            Some((Ok(from), changed_at)) if from == level => changed_at,
+            Some((Ok(from), _)) => {
+                updates.push(LevelUpdate {
+                    user: user.clone(),
+                    from,
+                    to: level,
+                    last_month,
+                });
+                now
+            }
+            _ => now,
+        };
+        levels.push(user_level::ActiveModel {
+            user_id: ActiveValue::Set(user.id),
+            level: ActiveValue::Set(level.to_string()),
+            last_month: ActiveValue::Set(to_db(last_month)),
+            changed_at: ActiveValue::Set(changed_at),
+        });
+    }
+
+    for chunk in levels.chunks(CHUNK_SIZE) {
+        user_level::Entity::insert_many(chunk.to_vec())
+            .on_conflict(
+                OnConflict::column(user_level::Column::UserId)
+                    .update_columns([
+                        user_level::Column::Level,
+                        user_level::Column::LastMonth,
+                        user_level::Column::ChangedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(db)
+            .await?;
+    }
+    Ok(updates)
+}
+
+/// レベルアップの投稿設定を取得する; 未設定の場合は無効として扱う
+pub async fn announcement(
+    db: &DatabaseConnection,
+    user_id: &str,
+) -> Result<announcement::Model, Error> {
+    let setting = announcement::Entity::find_by_id(user_id)
+        .one(db)
+        .await?
+        .unwrap_or(announcement::Model {
+            user_id: user_id.to_string(),
+            enabled: false,
+            use_own_token: false,
+        });
+    Ok(setting)
+}
+
+/// 目標の内容を検証する
+fn validate_goal(goal: &goal::Model, today: NaiveDate) -> Result<(), Error> {
+    match (&goal.target_level, goal.target_pix) {
+        (Some(level), None) => {
+            level
+                .parse::<PgnLevel>()
+                .map_err(|_| Error::InvalidGoal("unknown target_level"))?;
         }
-    };
+        (None, Some(pix)) if pix > 0 => {}
+        (None, Some(_)) => return Err(Error::InvalidGoal("target_pix must be positive")),
+        _ => {
+            return Err(Error::InvalidGoal(
+                "specify either target_level or target_pix",
+            ))
+        }
+    }
+    if goal.deadline <= today {
+        return Err(Error::InvalidGoal("deadline must be in the future"));
+    }
+    Ok(())
+}
 
-    Ok(Some(UserProfile {
-        user,
-        student,
-        created_at: now,
-        pgn,
-    }))
+/// ユーザの目標を期限の昇順で取得する
+pub async fn goals(db: &DatabaseConnection, user_id: &str) -> Result<Vec<goal::Model>, Error> {
+    let goals = goal::Entity::find()
+        .filter(goal::Column::UserId.eq(user_id))
+        .order_by_asc(goal::Column::Deadline)
+        .order_by_asc(goal::Column::Id)
+        .all(db)
+        .await?;
+    Ok(goals)
 }
 
-pub async fn active_users(db: &DatabaseConnection) -> Result<Option<Vec<user::Model>>, Error> {
-    let Some(refresh_log_item) = refreshed_users::Entity::find()
-        .column(refreshed_users::Column::Ulid)
-        .order_by_desc(refreshed_users::Column::Ulid)
+/// 目標を追加する
+pub async fn create_goal(
+    db: &DatabaseConnection,
+    now: DateTimeUtc,
+    user_id: &str,
+    goal: goal::Model,
+) -> Result<goal::Model, Error> {
+    validate_goal(&goal, now.with_timezone(&Local).date_naive())?;
+    let goal = goal::ActiveModel {
+        id: ActiveValue::NotSet,
+        user_id: ActiveValue::Set(user_id.to_string()),
+        target_level: ActiveValue::Set(goal.target_level),
+        target_pix: ActiveValue::Set(goal.target_pix),
+        deadline: ActiveValue::Set(goal.deadline),
+        created_at: ActiveValue::Set(now),
+    }
+    .insert(db)
+    .await?;
+    Ok(goal)
+}
+
+/// 目標を更新する; 他のユーザの目標は更新できない
+pub async fn update_goal(
+    db: &DatabaseConnection,
+    now: DateTimeUtc,
+    user_id: &str,
+    id: i32,
+    goal: goal::Model,
+) -> Result<Option<goal::Model>, Error> {
+    validate_goal(&goal, now.with_timezone(&Local).date_naive())?;
+    let Some(current) = goal::Entity::find_by_id(id)
+        .filter(goal::Column::UserId.eq(user_id))
         .one(db)
         .await?
     else {
         return Ok(None);
     };
+    let mut active: goal::ActiveModel = current.into();
+    active.target_level = ActiveValue::Set(goal.target_level);
+    active.target_pix = ActiveValue::Set(goal.target_pix);
+    active.deadline = ActiveValue::Set(goal.deadline);
+    Ok(Some(active.update(db).await?))
+}
 
-    let log_id = refresh_log_item.ulid;
+/// 目標を削除する; 削除した場合はtrue
+pub async fn delete_goal(db: &DatabaseConnection, user_id: &str, id: i32) -> Result<bool, Error> {
+    let result = goal::Entity::delete_many()
+        .filter(goal::Column::Id.eq(id))
+        .filter(goal::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected > 0)
+}
 
-    let users = user::Entity::find()
-        .join(
-            sea_orm::JoinType::LeftJoin,
-            refreshed_users::Relation::User.def().rev(),
+/// レベルアップの投稿設定を保存する。
+/// 自分のアカウントから投稿する場合は、投稿を許可したトークンが必要
+pub async fn set_announcement(
+    db: &DatabaseConnection,
+    setting: announcement::Model,
+) -> Result<announcement::Model, Error> {
+    if setting.use_own_token {
+        let token = mstdn_token::Entity::find_by_id(setting.user_id.clone())
+            .one(db)
+            .await?;
+        if !token.is_some_and(|t| t.can_post()) {
+            return Err(Error::MissingScope("write:statuses"));
+        }
+    }
+    announcement::Entity::insert(announcement::ActiveModel::from(setting.clone()))
+        .on_conflict(
+            OnConflict::column(announcement::Column::UserId)
+                .update_columns([
+                    announcement::Column::Enabled,
+                    announcement::Column::UseOwnToken,
+                ])
+                .to_owned(),
         )
-        .filter(refreshed_users::Column::Ulid.eq(log_id))
+        .exec(db)
+        .await?;
+    Ok(setting)
+}
+
+/// プロフィールの公開設定を取得する; 未設定の場合は非公開として扱う
+pub async fn public_profile(
+    db: &DatabaseConnection,
+    user_id: &str,
+) -> Result<public_profile::Model, Error> {
+    let setting = public_profile::Entity::find_by_id(user_id)
+        .one(db)
+        .await?
+        .unwrap_or(public_profile::Model {
+            user_id: user_id.to_string(),
+            enabled: false,
+        });
+    Ok(setting)
+}
+
+/// プロフィールの公開設定を保存する
+pub async fn set_public_profile(
+    db: &DatabaseConnection,
+    setting: public_profile::Model,
+) -> Result<public_profile::Model, Error> {
+    public_profile::Entity::insert(public_profile::ActiveModel::from(setting.clone()))
+        .on_conflict(
+            OnConflict::column(public_profile::Column::UserId)
+                .update_column(public_profile::Column::Enabled)
+                .to_owned(),
+        )
+        .exec(db)
+        .await?;
+    Ok(setting)
+}
+
+/// Pgrit IDのユーザがプロフィールを公開しているか; ユーザが存在しない場合もfalse
+pub async fn is_public(db: &DatabaseConnection, pgrit_id: &str) -> Result<bool, Error> {
+    let Some(user) = user::Entity::find()
+        .filter(user::Column::PgritId.eq(pgrit_id))
+        .one(db)
+        .await?
+    else {
+        return Ok(false);
+    };
+    Ok(public_profile(db, &user.id).await?.enabled)
+}
+
+/// 指定したユーザのうち、レベルアップの投稿を有効にしているユーザの設定とPgritのトークン
+pub async fn announcement_targets(
+    db: &DatabaseConnection,
+    user_ids: impl IntoIterator<Item = String>,
+) -> Result<HashMap<String, (announcement::Model, Option<mstdn_token::Model>)>, Error> {
+    let settings = announcement::Entity::find()
+        .filter(announcement::Column::Enabled.eq(true))
+        .filter(announcement::Column::UserId.is_in(user_ids))
+        .all(db)
+        .await?;
+    let mut tokens: HashMap<String, mstdn_token::Model> = mstdn_token::Entity::find()
+        .filter(mstdn_token::Column::UserId.is_in(settings.iter().map(|s| s.user_id.clone())))
         .all(db)
         .await?
         .into_iter()
-        .collect_vec();
+        .map(|t| (t.user_id.clone(), t))
+        .collect();
+    Ok(settings
+        .into_iter()
+        .map(|s| {
+            let token = tokens.remove(&s.user_id);
+            (s.user_id.clone(), (s, token))
+        })
+        .collect())
+}
 
-    Ok(Some(users))
+/// 期間内の日毎のPIXをユーザ毎に取得する
+pub async fn daily_pix(
+    db: &DatabaseConnection,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<HashMap<String, BTreeMap<NaiveDate, u32>>, Error> {
+    let mut daily: HashMap<String, BTreeMap<NaiveDate, u32>> = HashMap::new();
+    for pix in pix::Entity::find()
+        .filter(pix::Column::Date.gte(from))
+        .filter(pix::Column::Date.lt(to))
+        .all(db)
+        .await?
+    {
+        daily
+            .entry(pix.user_id)
+            .or_default()
+            .insert(pix.date, from_db(pix.amount));
+    }
+    Ok(daily)
+}
+
+/// 指定したユーザの学生情報を取得する
+pub async fn students(
+    db: &DatabaseConnection,
+    user_ids: impl IntoIterator<Item = String>,
+) -> Result<HashMap<String, student::Model>, Error> {
+    let students = student::Entity::find()
+        .filter(student::Column::UserId.is_in(user_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|s| (s.user_id.clone(), s))
+        .collect();
+    Ok(students)
+}
+
+/// 通知の送信を記録する。既に同じ通知を送っている場合はfalseを返す
+pub async fn mark_notified(
+    db: &DatabaseConnection,
+    now: DateTimeUtc,
+    kind: &str,
+    user_id: &str,
+    date: NaiveDate,
+) -> Result<bool, Error> {
+    let inserted = notification::Entity::insert(notification::ActiveModel {
+        kind: ActiveValue::Set(kind.to_string()),
+        user_id: ActiveValue::Set(user_id.to_string()),
+        date: ActiveValue::Set(date),
+        sent_at: ActiveValue::Set(now),
+    })
+    .on_conflict(
+        OnConflict::columns([
+            notification::Column::Kind,
+            notification::Column::UserId,
+            notification::Column::Date,
+        ])
+        .do_nothing()
+        .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await?;
+    Ok(inserted > 0)
+}
+
+/// 送信できなかった通知の記録を取り消し、次の機会に送り直せるようにする
+pub async fn unmark_notified(
+    db: &DatabaseConnection,
+    kind: &str,
+    user_id: &str,
+    date: NaiveDate,
+) -> Result<(), Error> {
+    notification::Entity::delete_by_id((kind.to_string(), user_id.to_string(), date))
+        .exec(db)
+        .await?;
+    Ok(())
 }
 
 pub async fn get_last_updated_at(db: &DatabaseConnection) -> Result<Option<DateTimeUtc>, Error> {
@@ -171,11 +1523,12 @@ fn create_student_activemodel(record: Record) -> Option<student::ActiveModel> {
     Some(student::ActiveModel {
         user_id: ActiveValue::Set(record.wallet_address.clone()),
         degree_step: ActiveValue::Set(degree_step),
-        grade: ActiveValue::Set(nth),
+        grade: ActiveValue::Set(nth.min(i16::MAX as u16) as i16),
         course: ActiveValue::Set(record.course?),
         level: ActiveValue::Set(record.level?),
         sex: ActiveValue::Set(record.sex?),
         join_date: ActiveValue::Set(record.join_date?),
+        join_month: ActiveValue::Set(record.join_month),
         office: ActiveValue::Set(record.office?),
         email: ActiveValue::Set(record.email?),
         email_of_4nonome: ActiveValue::Set(record.email_of_4nonome?),
@@ -204,6 +1557,26 @@ pub enum SignupError {
     SeaOrmError(#[from] sea_orm::error::DbErr),
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum RefreshError {
+    #[error("Failed to get the last updated time: {0}")]
+    LastUpdatedAt(#[source] Error),
+    #[error("Failed to get the active users: {0}")]
+    ActiveUsers(#[source] Error),
+    #[error("Failed to fetch records: {0}")]
+    Fetch(#[source] Error),
+    #[error("Failed to insert records: {0}")]
+    Insert(#[source] Error),
+    #[error("Failed to record the refresh job: {0}")]
+    Job(#[source] Error),
+}
+
+/// ログイン時に要求するスコープ
+pub const LOGIN_SCOPE: &str = "read:accounts";
+/// 自分のアカウントからのレベルアップの投稿を許可する際に要求するスコープ
+pub const POST_SCOPE: &str = "read:accounts write:statuses";
+
+#[allow(clippy::too_many_arguments)]
 pub async fn signup(
     db: &DatabaseConnection,
     pgrit_origin: &str,
@@ -212,17 +1585,18 @@ pub async fn signup(
     pgrit_client_key: &str,
     pgrit_client_secret: &str,
     code: &str,
+    scope: &str,
 ) -> Result<user::Model, SignupError> {
     // auhtorization codeを使ってtokenを取得
     let data = reqwest::Client::new()
-        .post(&format!("{}/oauth/token", pgrit_origin))
+        .post(format!("{}/oauth/token", pgrit_origin))
         .form(&[
             ("grant_type", "authorization_code"),
             ("redirect_uri", callback_url_ours),
             ("client_id", pgrit_client_key),
             ("client_secret", pgrit_client_secret),
             ("code", code),
-            ("scope", "read:accounts"),
+            ("scope", scope),
         ])
         .send()
         .await?
@@ -231,6 +1605,8 @@ pub async fn signup(
 
     let json: Value = serde_json::from_str(&data)?;
     let token = query_value!(json.access_token -> str).context("token not found")?;
+    // 許可されたスコープが返されない場合は要求したスコープとみなす
+    let granted_scope = query_value!(json.scope -> str).unwrap_or(scope);
 
     // tokenを使ってユーザ情報を取得
     let client = reqwest::Client::builder()
@@ -264,33 +1640,145 @@ pub async fn signup(
         u
     };
 
-    mstdn_token::Entity::insert(mstdn_token::ActiveModel {
-        user_id: ActiveValue::Set(user.id.clone()),
-        access_token: ActiveValue::Set(token.to_string()),
-        authorization_code: ActiveValue::Set(code.to_string()),
-    })
-    .on_conflict(
-        OnConflict::column(mstdn_token::Column::UserId)
-            .update_columns([
-                mstdn_token::Column::AccessToken,
-                mstdn_token::Column::AuthorizationCode,
-            ])
-            .to_owned(),
-    )
-    .exec(db)
-    .await?;
+    let token = mstdn_token::Model {
+        user_id: user.id.clone(),
+        access_token: token.to_string(),
+        authorization_code: code.to_string(),
+        scope: Some(granted_scope.to_string()),
+    };
+    // 投稿を許可したトークンを、その後のログインで得た読み取りのみのトークンで上書きしない
+    let current = mstdn_token::Entity::find_by_id(user.id.clone())
+        .one(db)
+        .await?;
+    if current.is_some_and(|t| t.can_post() && !token.can_post()) {
+        return Ok(user);
+    }
+    mstdn_token::Entity::insert(mstdn_token::ActiveModel::from(token))
+        .on_conflict(
+            OnConflict::column(mstdn_token::Column::UserId)
+                .update_columns([
+                    mstdn_token::Column::AccessToken,
+                    mstdn_token::Column::AuthorizationCode,
+                    mstdn_token::Column::Scope,
+                ])
+                .to_owned(),
+        )
+        .exec(db)
+        .await?;
     Ok(user)
 }
 
+/// 1回のリフレッシュの経過
+#[derive(Debug, Default)]
+pub struct RefreshReport {
+    /// 取得を要求した期間の初日
+    pub start_date: Option<NaiveDate>,
+    /// 取得を要求した期間の最終日
+    pub end_date: Option<NaiveDate>,
+    /// 取得したレコード数
+    pub records_fetched: Option<u32>,
+    /// upsertしたPIXの行数
+    pub rows_upserted: Option<u32>,
+    /// 30日分を再取得したか
+    pub refetched: bool,
+    /// 読み込み時に問題のあったレコード
+    pub warnings: Vec<RecordReport>,
+}
+
+/// リフレッシュジョブの開始を記録し、そのIDを返す
+pub async fn start_refresh_job(db: &DatabaseConnection, now: DateTimeUtc) -> Result<String, Error> {
+    let ulid = Ulid::from_datetime(now.into()).to_string();
+    refresh_job::ActiveModel {
+        ulid: ActiveValue::Set(ulid.clone()),
+        status: ActiveValue::Set(RefreshStatus::Running),
+        started_at: ActiveValue::Set(now),
+        finished_at: ActiveValue::Set(None),
+        start_date: ActiveValue::Set(None),
+        end_date: ActiveValue::Set(None),
+        records_fetched: ActiveValue::Set(None),
+        rows_upserted: ActiveValue::Set(None),
+        refetched: ActiveValue::Set(false),
+        error: ActiveValue::Set(None),
+        warnings: ActiveValue::Set(None),
+    }
+    .insert(db)
+    .await?;
+    Ok(ulid)
+}
+
+/// リフレッシュジョブの終了を記録する
+pub async fn finish_refresh_job(
+    db: &DatabaseConnection,
+    ulid: String,
+    now: DateTimeUtc,
+    status: RefreshStatus,
+    report: RefreshReport,
+    error: Option<String>,
+) -> Result<(), Error> {
+    refresh_job::ActiveModel {
+        ulid: ActiveValue::Unchanged(ulid),
+        status: ActiveValue::Set(status),
+        finished_at: ActiveValue::Set(Some(now)),
+        start_date: ActiveValue::Set(report.start_date),
+        end_date: ActiveValue::Set(report.end_date),
+        records_fetched: ActiveValue::Set(report.records_fetched.map(to_db)),
+        rows_upserted: ActiveValue::Set(report.rows_upserted.map(to_db)),
+        refetched: ActiveValue::Set(report.refetched),
+        error: ActiveValue::Set(error),
+        warnings: ActiveValue::Set(
+            (!report.warnings.is_empty())
+                .then(|| serde_json::to_value(&report.warnings))
+                .transpose()?,
+        ),
+        ..Default::default()
+    }
+    .update(db)
+    .await?;
+    Ok(())
+}
+
+/// 記録に残さないリフレッシュジョブを取り消す
+pub async fn discard_refresh_job(db: &DatabaseConnection, ulid: String) -> Result<(), Error> {
+    refresh_job::Entity::delete_by_id(ulid).exec(db).await?;
+    Ok(())
+}
+
+/// 最新のリフレッシュジョブの状態; 一度も実行されていない場合はNone
+pub async fn last_refresh_status(db: &DatabaseConnection) -> Result<Option<RefreshStatus>, Error> {
+    let job = refresh_job::Entity::find()
+        .order_by_desc(refresh_job::Column::Ulid)
+        .one(db)
+        .await?;
+    Ok(job.map(|j| j.status))
+}
+
+/// 新しい順にリフレッシュジョブの履歴を取得する
+pub async fn refresh_jobs(
+    db: &DatabaseConnection,
+    limit: u64,
+) -> Result<Vec<refresh_job::Model>, Error> {
+    let jobs = refresh_job::Entity::find()
+        .order_by_desc(refresh_job::Column::Ulid)
+        .limit(limit)
+        .all(db)
+        .await?;
+    Ok(jobs)
+}
+
+/// レコードをデータベースに書き込み、upsertしたPIXの行数を返す。
+/// `refreshed`がfalseの場合はリフレッシュの記録とPIXの取得元の内訳を残さない;
+/// 最終更新日時やアクティブなユーザ、プロフィールの取得元の内訳は変わらない
 pub async fn insert(
     db: &DatabaseConnection,
     now: DateTimeUtc,
     records: impl IntoIterator<Item = Record>,
-) -> Result<(), Error> {
+    refreshed: bool,
+) -> Result<usize, Error> {
     let log_id = Ulid::from_datetime(now.into()).to_string();
     let mut users = Vec::new();
     let mut refreshed_user_item = Vec::new();
     let mut pixes = Vec::new();
+    let mut sources = Vec::new();
     let mut students = Vec::new();
     for record in records {
         if let Some(student) = create_student_activemodel(record.clone()) {
@@ -304,18 +1792,49 @@ pub async fn insert(
             user_id: ActiveValue::Set(record.wallet_address.clone()),
             ulid: ActiveValue::Set(log_id.clone()),
         };
+        // 集計期間は日毎の内訳の日付から求める。取り込んだ過去のデータの内訳は最新のものではないので残さない
+        if let Some((start_date, end_date)) = record
+            .daily_totals
+            .keys()
+            .minmax()
+            .into_option()
+            .filter(|_| refreshed)
+        {
+            sources.push(pix_source::ActiveModel {
+                ulid: ActiveValue::Set(log_id.clone()),
+                user_id: ActiveValue::Set(record.wallet_address.clone()),
+                start_date: ActiveValue::Set(*start_date),
+                end_date: ActiveValue::Set(*end_date),
+                pgrit: ActiveValue::Set(to_db(record.total_pgrit)),
+                dawn: ActiveValue::Set(to_db(record.total_dawn)),
+                other: ActiveValue::Set(to_db(record.total_other)),
+            });
+        }
         let pix = record
             .daily_totals
             .into_iter()
             .map(|(date, amount)| pix::ActiveModel {
                 user_id: ActiveValue::Set(record.wallet_address.clone()),
                 date: ActiveValue::Set(date),
-                amount: ActiveValue::Set(amount),
+                amount: ActiveValue::Set(to_db(amount)),
             });
         users.push(user);
-        refreshed_user_item.push(tb);
+        if refreshed {
+            refreshed_user_item.push(tb);
+        }
         pixes.extend(pix);
     }
+    let rows_upserted = pixes.len();
+    // 実績はユーザ毎に書き込んだ最も古い日以降について判定し直す
+    let mut written: HashMap<String, NaiveDate> = HashMap::new();
+    for pix in &pixes {
+        if let (ActiveValue::Set(user_id), ActiveValue::Set(date)) = (&pix.user_id, &pix.date) {
+            written
+                .entry(user_id.clone())
+                .and_modify(|d| *d = (*d).min(*date))
+                .or_insert(*date);
+        }
+    }
 
     db.transaction(|db| {
         Box::pin(async move {
@@ -338,6 +1857,7 @@ pub async fn insert(
                             student::Column::Level,
                             student::Column::Sex,
                             student::Column::JoinDate,
+                            student::Column::JoinMonth,
                             student::Column::Office,
                             student::Column::Email,
                             student::Column::EmailOf4nonome,
@@ -365,6 +1885,21 @@ pub async fn insert(
                 .do_nothing()
                 .exec(db)
                 .await?;
+            pix_source::Entity::insert_many(sources)
+                .on_conflict(
+                    OnConflict::columns([pix_source::Column::Ulid, pix_source::Column::UserId])
+                        .update_columns([
+                            pix_source::Column::StartDate,
+                            pix_source::Column::EndDate,
+                            pix_source::Column::Pgrit,
+                            pix_source::Column::Dawn,
+                            pix_source::Column::Other,
+                        ])
+                        .to_owned(),
+                )
+                .do_nothing()
+                .exec(db)
+                .await?;
 
             // 多すぎてトークン制限に引っかかるので分割
             while !pixes.is_empty() {
@@ -391,5 +1926,446 @@ pub async fn insert(
     })
     .await
     .context("Failed to insert records into the database")?;
+
+    // 実績の判定に失敗しても書き込んだPIXは有効なので、ログに残して続行する
+    if let Err(e) = update_achievements(db, now, written).await {
+        eprintln!("Failed to update achievements: {:?}", e);
+    }
+    Ok(rows_upserted)
+}
+
+#[derive(FromQueryResult)]
+struct PixBefore {
+    user_id: String,
+    total: Option<i64>,
+    best: Option<i32>,
+}
+
+/// 実績の判定に読み込んだユーザの履歴
+#[derive(Default)]
+struct LoadedHistory {
+    daily: BTreeMap<NaiveDate, u32>,
+    total_before: u32,
+    best_before: u32,
+}
+
+/// ユーザ毎にPIXを書き込んだ最も古い日を受け取り、それ以降に得た実績を判定して保存する。
+/// 履歴はその日から`achievement::LOOKBACK_DAYS`日遡った分のみ読み込み、それより前は合計と最高値のみ求める。
+/// まだ実績の無いユーザは履歴全体から判定する。
+/// 一度得た実績は書き換えず、自己ベストのような記録を更新していく実績のみ上書きする
+pub async fn update_achievements(
+    db: &DatabaseConnection,
+    now: DateTimeUtc,
+    written: HashMap<String, NaiveDate>,
+) -> Result<(), Error> {
+    let today = now.with_timezone(&Local).date_naive();
+    let user_ids = written.keys().cloned().collect::<Vec<_>>();
+    let mut evaluated = HashSet::new();
+    for user_ids in user_ids.chunks(CHUNK_SIZE) {
+        evaluated.extend(
+            achievement::Entity::find()
+                .select_only()
+                .column(achievement::Column::UserId)
+                .distinct()
+                .filter(achievement::Column::UserId.is_in(user_ids.iter().cloned()))
+                .into_tuple::<String>()
+                .all(db)
+                .await?,
+        );
+    }
+    // 読み込みを始める日毎にまとめる; Noneは履歴全体
+    let mut by_start: BTreeMap<Option<NaiveDate>, Vec<String>> = BTreeMap::new();
+    for (user_id, date) in written {
+        let start = evaluated
+            .contains(&user_id)
+            .then(|| date - chrono::Duration::days(crate::achievement::LOOKBACK_DAYS));
+        by_start.entry(start).or_default().push(user_id);
+    }
+
+    let mut history: HashMap<String, LoadedHistory> = HashMap::new();
+    for (start, user_ids) in &by_start {
+        for user_ids in user_ids.chunks(CHUNK_SIZE) {
+            let mut query = pix::Entity::find()
+                .filter(pix::Column::UserId.is_in(user_ids.iter().cloned()))
+                .filter(pix::Column::Date.lt(today)); // 今日のデータは含めない
+            if let Some(start) = start {
+                query = query.filter(pix::Column::Date.gte(*start));
+            }
+            for pix in query.all(db).await? {
+                history
+                    .entry(pix.user_id)
+                    .or_default()
+                    .daily
+                    .insert(pix.date, from_db(pix.amount));
+            }
+            let Some(start) = start else {
+                continue;
+            };
+            for before in pix::Entity::find()
+                .select_only()
+                .column(pix::Column::UserId)
+                .column_as(amount_sum(db), "total")
+                .column_as(pix::Column::Amount.max(), "best")
+                .filter(pix::Column::UserId.is_in(user_ids.iter().cloned()))
+                .filter(pix::Column::Date.lt(*start))
+                .group_by(pix::Column::UserId)
+                .into_model::<PixBefore>()
+                .all(db)
+                .await?
+            {
+                let loaded = history.entry(before.user_id).or_default();
+                loaded.total_before = before.total.unwrap_or(0).clamp(0, u32::MAX as i64) as u32;
+                loaded.best_before = before.best.map(from_db).unwrap_or(0);
+            }
+        }
+    }
+    let seasons = seasons(db).await?;
+
+    let mut fixed = Vec::new();
+    let mut replaceable = Vec::new();
+    for (user_id, loaded) in &history {
+        let daily = &loaded.daily;
+        let Some(first) = daily.keys().next() else {
+            continue;
+        };
+        // 読み込んだ範囲の始めのPgnLevelは低く求まるが、それまでに到達したレベルの実績は保存済み
+        let levels = rolling_levels(daily, &seasons, *first + chrono::Duration::days(1), today);
+        let history = History {
+            daily,
+            levels: &levels,
+            total_before: loaded.total_before,
+            best_before: loaded.best_before,
+        };
+        for rule in RULES {
+            let awards = rule
+                .evaluate(&history)
+                .into_iter()
+                .map(|a| achievement::ActiveModel {
+                    user_id: ActiveValue::Set(user_id.clone()),
+                    kind: ActiveValue::Set(a.kind.to_string()),
+                    tier: ActiveValue::Set(a.tier),
+                    value: ActiveValue::Set(to_db(a.value)),
+                    earned_on: ActiveValue::Set(a.earned_on),
+                });
+            if rule.replaceable() {
+                replaceable.extend(awards);
+            } else {
+                fixed.extend(awards);
+            }
+        }
+    }
+
+    let key = [
+        achievement::Column::UserId,
+        achievement::Column::Kind,
+        achievement::Column::Tier,
+    ];
+    for items in fixed.chunks(CHUNK_SIZE) {
+        achievement::Entity::insert_many(items.to_vec())
+            .on_conflict(OnConflict::columns(key).do_nothing().to_owned())
+            .do_nothing()
+            .exec(db)
+            .await?;
+    }
+    for items in replaceable.chunks(CHUNK_SIZE) {
+        achievement::Entity::insert_many(items.to_vec())
+            .on_conflict(
+                OnConflict::columns(key)
+                    .update_columns([achievement::Column::Value, achievement::Column::EarnedOn])
+                    .to_owned(),
+            )
+            .do_nothing()
+            .exec(db)
+            .await?;
+    }
     Ok(())
 }
+
+/// ユーザの実績を得た日の昇順で取得する
+pub async fn achievements(
+    db: &DatabaseConnection,
+    user_id: &str,
+) -> Result<Vec<achievement::Model>, Error> {
+    let achievements = achievement::Entity::find()
+        .filter(achievement::Column::UserId.eq(user_id))
+        .order_by_asc(achievement::Column::EarnedOn)
+        .order_by_asc(achievement::Column::Kind)
+        .all(db)
+        .await?;
+    Ok(achievements)
+}
+
+/// 保存しておいた取得元APIのレスポンスを1件ずつ検証し、問題のないレコードのみを書き込む
+pub async fn import(
+    db: &DatabaseConnection,
+    now: DateTimeUtc,
+    values: Vec<Value>,
+    dry_run: bool,
+) -> Result<ImportReport, Error> {
+    let mut report = ImportReport {
+        total: values.len(),
+        ..Default::default()
+    };
+    let mut records = Vec::new();
+    for (index, value) in values.into_iter().enumerate() {
+        let id = query_value!(value.id -> str).map(str::to_string);
+        let wallet_address = query_value!(value.walletAddress -> str).map(str::to_string);
+        match Record::parse_checked(value) {
+            Ok(record) => records.push(record),
+            Err(errors) => report.failed.push(RecordReport {
+                index,
+                id,
+                wallet_address,
+                errors,
+                skipped: true,
+            }),
+        }
+    }
+    report.imported = records.len();
+    if !dry_run && !records.is_empty() {
+        // 過去のデータの取り込みはリフレッシュではないので、次のリフレッシュの取得範囲に影響させない
+        report.rows_upserted = insert(db, now, records, false).await?;
+    }
+    Ok(report)
+}
+
+/// 保存されているデータを取得元APIと同じ形式で書き出す。書き出したものはそのまま読み込み直せる。
+/// PIXの期間はHTTPの書き出しと同じく[start, end)の半開区間で、`end`の日は含まない
+pub async fn export(
+    db: &DatabaseConnection,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+) -> Result<Vec<Value>, Error> {
+    let users = user::Entity::find()
+        .find_also_related(student::Entity)
+        .order_by_asc(user::Column::PgritId)
+        .all(db)
+        .await?;
+
+    let mut query = pix::Entity::find().order_by_asc(pix::Column::Date);
+    if let Some(start) = start {
+        query = query.filter(pix::Column::Date.gte(start));
+    }
+    if let Some(end) = end {
+        query = query.filter(pix::Column::Date.lt(end));
+    }
+    let mut daily: HashMap<String, Vec<pix::Model>> = HashMap::new();
+    for pix in query.all(db).await? {
+        daily.entry(pix.user_id.clone()).or_default().push(pix);
+    }
+
+    // 取得元ごとの合計は直近のリフレッシュのものを用いる
+    let mut sources: HashMap<String, pix_source::Model> = HashMap::new();
+    for source in pix_source::Entity::find()
+        .order_by_asc(pix_source::Column::Ulid)
+        .all(db)
+        .await?
+    {
+        sources.insert(source.user_id.clone(), source);
+    }
+
+    let records = users
+        .into_iter()
+        .map(|(user, student)| {
+            let mut record = serde_json::Map::new();
+            record.insert("id".into(), user.pgrit_id.into());
+            record.insert("walletAddress".into(), user.id.clone().into());
+            if let Some(s) = student {
+                let grade = Grade {
+                    degree_step: s.degree_step,
+                    nth: s.grade.max(0) as u16,
+                };
+                record.insert("grade".into(), grade.to_string().into());
+                record.insert("course".into(), s.course.into());
+                record.insert("level".into(), s.level.to_string().into());
+                record.insert("sex".into(), s.sex.to_string().into());
+                record.insert("joinDate".into(), s.join_date.to_string().into());
+                if let Some(join_month) = s.join_month {
+                    record.insert("joinMonth".into(), join_month.into());
+                }
+                record.insert("office".into(), s.office.into());
+                record.insert("email".into(), s.email.into());
+                record.insert("emailOf4nonome".into(), s.email_of_4nonome.into());
+                record.insert("university".into(), s.university.into());
+                record.insert("major".into(), s.major.into());
+                record.insert(
+                    "leaveDate".into(),
+                    s.leave_date.map(|d| d.to_string()).into(),
+                );
+                record.insert("active".into(), s.active.into());
+                record.insert("slackId".into(), s.slack_id.into());
+                record.insert("discordId".into(), s.discord_id.unwrap_or_default().into());
+            }
+
+            let pixes = daily.remove(&user.id).unwrap_or_default();
+            let total: u32 = pixes.iter().map(|p| from_db(p.amount)).sum();
+            let source = sources.remove(&user.id);
+            record.insert("total".into(), total.into());
+            record.insert(
+                "total_pgrit".into(),
+                source.as_ref().map_or(0, |s| from_db(s.pgrit)).into(),
+            );
+            record.insert(
+                "total_dawn".into(),
+                source.as_ref().map_or(0, |s| from_db(s.dawn)).into(),
+            );
+            record.insert(
+                "total_other".into(),
+                source.as_ref().map_or(0, |s| from_db(s.other)).into(),
+            );
+            for pix in pixes {
+                record.insert(pix.date.to_string(), from_db(pix.amount).into());
+            }
+            Value::Object(record)
+        })
+        .collect();
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    /// 既定の閾値からsilver_minだけ変えたシーズン
+    fn season_from(effective_from: &str, silver_min: i32) -> season::Model {
+        season::Model {
+            id: 1,
+            name: "test".to_string(),
+            effective_from: date(effective_from),
+            bronze_min: 500,
+            silver_min,
+            gold_min: 2500,
+            platinum_min: 5000,
+            diamond_min: 10000,
+            master_min: 20000,
+            grandmaster_min: 35000,
+        }
+    }
+
+    #[test]
+    fn rolling_levels_uses_previous_30_days() {
+        let daily = BTreeMap::from([(date("2024-03-01"), 1000)]);
+        let points = rolling_levels(&daily, &[], date("2024-03-01"), date("2024-04-01"));
+
+        let at = |d: &str| points.iter().find(|p| p.date == date(d)).unwrap();
+        // 当日のPIXはその日のPgnLevelに含めない
+        assert_eq!(at("2024-03-01").window_pix, 0);
+        assert_eq!(at("2024-03-01").level, PgnLevel::Iron);
+        assert_eq!(at("2024-03-02").window_pix, 1000);
+        assert_eq!(at("2024-03-02").level, PgnLevel::Silver);
+        // 30日後まで期間に含まれ、31日後に外れる
+        assert_eq!(at("2024-03-31").level, PgnLevel::Silver);
+        assert_eq!(at("2024-04-01").window_pix, 0);
+        assert_eq!(at("2024-04-01").level, PgnLevel::Iron);
+    }
+
+    #[test]
+    fn rolling_levels_matches_window_at() {
+        let daily: BTreeMap<NaiveDate, u32> = date("2024-01-01")
+            .iter_days()
+            .take(90)
+            .enumerate()
+            .filter(|(i, _)| i % 3 != 0)
+            .map(|(i, d)| (d, i as u32 * 7))
+            .collect();
+        for point in rolling_levels(&daily, &[], date("2024-01-15"), date("2024-04-15")) {
+            let window = Window::at(point.date, point.date).unwrap();
+            let expected: u32 = daily
+                .range(window.from..=window.to)
+                .map(|(_, amount)| amount)
+                .sum();
+            assert_eq!(point.window_pix, expected, "{}", point.date);
+        }
+    }
+
+    #[test]
+    fn rolling_levels_uses_season_in_force_on_previous_day() {
+        let daily = BTreeMap::from([(date("2024-03-01"), 1000)]);
+        let seasons = [season_from("2024-03-05", 2000)];
+        let points = rolling_levels(&daily, &seasons, date("2024-03-05"), date("2024-03-06"));
+        // 3/5のPgnLevelは3/4に有効な既定の閾値で決まる
+        assert_eq!(points[0].level, PgnLevel::Silver);
+        assert_eq!(points[1].level, PgnLevel::Bronze);
+    }
+
+    #[test]
+    fn window_is_always_30_days() {
+        let today = date("2024-03-31");
+        let window = Window::ending(date("2024-03-30"), today).unwrap();
+        assert_eq!(window.from, date("2024-03-01"));
+        assert_eq!(window.to, date("2024-03-30"));
+        assert_eq!(window, Window::at(today, today).unwrap());
+        // 今日を含む期間は扱えない
+        assert!(Window::ending(today, today).is_err());
+        assert!(Window::at(date("2024-04-01"), today).is_err());
+        // 表現できる日付の範囲外にはみ出す
+        assert!(Window::at(NaiveDate::MIN, today).is_err());
+    }
+
+    #[test]
+    fn forecast_drops_days_leaving_the_window() {
+        let today = date("2024-04-01");
+        let daily = HashMap::from([
+            // 期間[3/2, 3/31]より前
+            (date("2024-03-01"), 5000),
+            (date("2024-03-02"), 600),
+            (date("2024-03-03"), 300),
+            (date("2024-03-20"), 400),
+            // 今日のPIXは含めない
+            (today, 5000),
+        ]);
+        let f = forecast(&daily, &[], today, PgnLevel::Silver);
+
+        assert_eq!(f.days.len(), FORECAST_DAYS as usize);
+        assert_eq!(f.days[0].date, date("2024-04-02"));
+        assert_eq!(f.days[0].expiring, 600);
+        assert_eq!(f.days[0].window_pix, 700);
+        assert_eq!(f.days[0].level, PgnLevel::Bronze);
+        assert_eq!(f.days[1].expiring, 300);
+        assert_eq!(f.days[1].window_pix, 400);
+        assert_eq!(f.days[1].level, PgnLevel::Iron);
+        assert!(f.days[2..]
+            .iter()
+            .all(|d| d.expiring == 0 && d.window_pix == 400));
+        assert_eq!(f.expiring, 900);
+        // 1日で300、2日間で600不足する
+        assert_eq!(f.required_daily, 300);
+    }
+
+    #[test]
+    fn forecast_switches_thresholds_at_season_boundary() {
+        let today = date("2024-04-01");
+        let daily = HashMap::from([(date("2024-03-20"), 1500)]);
+        let seasons = [season_from("2024-04-03", 2000)];
+        let f = forecast(&daily, &seasons, today, PgnLevel::Silver);
+
+        // 4/4から前日の4/3に有効な新しい閾値になる
+        assert_eq!(f.days[1].date, date("2024-04-03"));
+        assert_eq!(f.days[1].level, PgnLevel::Silver);
+        assert_eq!(f.days[2].level, PgnLevel::Bronze);
+        assert_eq!(f.expiring, 0);
+        // 3日間で500不足する
+        assert_eq!(f.required_daily, 167);
+    }
+
+    #[test]
+    fn forecast_without_pix() {
+        let f = forecast(&HashMap::new(), &[], date("2024-04-01"), PgnLevel::Iron);
+        assert_eq!(f.expiring, 0);
+        assert_eq!(f.required_daily, 0);
+        assert!(f
+            .days
+            .iter()
+            .all(|d| d.window_pix == 0 && d.level == PgnLevel::Iron));
+    }
+
+    #[test]
+    fn rolling_levels_empty_range() {
+        let daily = BTreeMap::from([(date("2024-03-01"), 1000)]);
+        assert!(rolling_levels(&daily, &[], date("2024-03-02"), date("2024-03-01")).is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand};
+
+/// PGNのPIXを集計するサーバ
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    /// 省略した場合はserveと同じ
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// マイグレーションを適用してサーバを起動する
+    Serve,
+    /// マイグレーションを操作する
+    Migrate {
+        #[command(subcommand)]
+        command: MigrateCommand,
+    },
+    /// 取得元APIから一度だけデータを取得して書き込む
+    Refresh {
+        /// 取得元APIのURL
+        #[arg(long, env = "FETCH_URL")]
+        url: String,
+        /// 取得する期間の初日; 省略した場合は終了日の29日前
+        #[arg(long)]
+        start: Option<NaiveDate>,
+        /// 取得する期間の最終日; 省略した場合は今日
+        #[arg(long)]
+        end: Option<NaiveDate>,
+    },
+    /// 保存しておいた取得元APIのレスポンスを検証し、問題のないレコードを書き込む
+    Import {
+        /// JSONファイルのパス
+        file: PathBuf,
+        /// 検証結果をJSONで書き出すパス
+        #[arg(long)]
+        report: Option<PathBuf>,
+        /// 検証のみ行い、書き込まない
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 保存されているデータを取得元APIと同じ形式のJSONで書き出す
+    Export {
+        /// 出力先; 省略した場合は標準出力
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// 書き出すPIXの期間の開始日(この日を含む)
+        #[arg(long)]
+        start: Option<NaiveDate>,
+        /// 書き出すPIXの期間の終了日(この日を含まない)
+        #[arg(long)]
+        end: Option<NaiveDate>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MigrateCommand {
+    /// 未適用のマイグレーションを適用する
+    Up {
+        /// 適用する数; 省略した場合は全て
+        #[arg(short)]
+        n: Option<u32>,
+    },
+    /// 適用済みのマイグレーションを取り消す
+    Down {
+        /// 取り消す数
+        #[arg(short, default_value_t = 1)]
+        n: u32,
+    },
+    /// マイグレーションの適用状況を表示する
+    Status,
+}
@@ -1,11 +1,26 @@
+mod cli;
+
+use std::io::Write;
+
+use chrono::{Local, Utc};
+use clap::Parser;
+use cli::{Cli, Command, MigrateCommand};
+use entity::import_report::RecordReport;
 use migration::{Migrator, MigratorTrait};
 use sea_orm::{ConnectOptions, Database};
 use serde::Deserialize;
+use serde_json::Value;
+use server::usecase;
 
 #[derive(Deserialize)]
 struct Environment {
-    #[serde(flatten)]
-    server_config: server::Config,
+    /// 接続先のデータベース; sqlite://, postgres://, mysql:// に対応
+    #[serde(default = "default_database_url")]
+    database_url: String,
+}
+
+fn default_database_url() -> String {
+    "sqlite://pgnpg.sqlite?mode=rwc".to_string()
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -16,21 +31,95 @@ enum Error {
     Entity(#[from] entity::error::Error),
     #[error(transparent)]
     Db(#[from] sea_orm::error::DbErr),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
+    let cli = Cli::parse();
     let env = envy::from_env::<Environment>()?;
 
     // Connect to the database
-    let connect_options = ConnectOptions::new("sqlite://pgnpg.sqlite?mode=rwc");
+    let connect_options = ConnectOptions::new(env.database_url);
     let db = Database::connect(connect_options).await?;
 
-    // Run the migration
-    Migrator::up(&db, None).await?;
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            // flattenするとenvyが数値や配列を解釈できないので個別に読み込む
+            let server_config = envy::from_env::<server::Config>()?;
+
+            // Run the migration
+            Migrator::up(&db, None).await?;
 
-    // Run the server
-    server::run(db, env.server_config).await;
+            // Run the server
+            server::run(db, server_config).await;
+        }
+        Command::Migrate { command } => match command {
+            MigrateCommand::Up { n } => Migrator::up(&db, n).await?,
+            MigrateCommand::Down { n } => Migrator::down(&db, Some(n)).await?,
+            MigrateCommand::Status => {
+                for migration in Migrator::get_migration_with_status(&db).await? {
+                    println!("{}\t{}", migration.status(), migration.name());
+                }
+            }
+        },
+        Command::Refresh { url, start, end } => {
+            let end = end.unwrap_or_else(|| Utc::now().with_timezone(&Local).date_naive());
+            let start = start.unwrap_or(end - chrono::Duration::days(29));
+            let (records, warnings) = usecase::fetch_with_retry(&url, start, end).await?;
+            print_record_reports(&warnings);
+            let count = records.len();
+            let rows = usecase::insert(&db, Utc::now(), records, true).await?;
+            println!("{} records fetched, {} pix rows upserted.", count, rows);
+        }
+        Command::Import {
+            file,
+            report: report_path,
+            dry_run,
+        } => {
+            let values: Vec<Value> = serde_json::from_str(&std::fs::read_to_string(file)?)?;
+            let report = usecase::import(&db, Utc::now(), values, dry_run).await?;
+            print_record_reports(&report.failed);
+            if let Some(path) = report_path {
+                serde_json::to_writer_pretty(std::fs::File::create(path)?, &report)?;
+            }
+            println!(
+                "{} of {} records valid, {} failed, {} pix rows upserted.",
+                report.imported,
+                report.total,
+                report.failed.len(),
+                report.rows_upserted
+            );
+        }
+        Command::Export { output, start, end } => {
+            let records = usecase::export(&db, start, end).await?;
+            let mut writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(std::fs::File::create(path)?),
+                None => Box::new(std::io::stdout().lock()),
+            };
+            serde_json::to_writer_pretty(&mut writer, &records)?;
+            writeln!(writer)?;
+        }
+    }
 
     Ok(())
 }
+
+/// 問題のあったレコードのフィールドを1行ずつ標準エラー出力に書き出す
+fn print_record_reports(reports: &[RecordReport]) {
+    for record in reports {
+        for error in &record.errors {
+            eprintln!(
+                "#{} ({}): {}: {} ({})",
+                record.index,
+                record.id.as_deref().unwrap_or("-"),
+                error.field,
+                error.message,
+                error.value
+            );
+        }
+    }
+}